@@ -1,11 +1,24 @@
 use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::gcp::client::SecretInfo;
+use crate::gcp::SecretManagerClient;
 
 const CACHE_FILE_NAME: &str = "cache.json";
-const DEFAULT_TTL_SECONDS: i64 = 300; // 5 minutes
+const DEFAULT_TTL_SECONDS: i64 = 300; // 5 minutes, unless overridden by config
+/// Listings go stale as soon as anyone adds a secret, so they expire sooner
+const DEFAULT_LIST_TTL_SECONDS: i64 = 60;
+const KEYRING_SERVICE: &str = "ksecret";
+const KEYRING_USER: &str = "cache-key";
+const NONCE_LEN: usize = 12;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct CacheEntry {
@@ -13,35 +26,102 @@ struct CacheEntry {
     expires_at: DateTime<Utc>,
 }
 
+/// On-disk representation of the cache; values are encrypted
 #[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    entries: HashMap<String, CacheEntry>,
+    /// Secret listings (as JSON) by project, prefix and environment
+    #[serde(default)]
+    lists: HashMap<String, CacheEntry>,
+}
+
 pub struct Cache {
+    /// Decrypted entries, only ever held in memory
     entries: HashMap<String, CacheEntry>,
+
+    /// Decrypted secret listings by project, prefix and environment
+    lists: HashMap<String, CacheEntry>,
+
+    /// Lifetime of newly cached entries, taken from config at load time
+    ttl: Duration,
+
+    /// Lifetime of newly cached listings
+    list_ttl: Duration,
+
+    /// Key used to encrypt values at rest
+    key: Key,
+
+    /// Config whose (per-environment) project and prefix scope the keys, so
+    /// secrets of the same name elsewhere don't share entries
+    config: Config,
 }
 
 impl Cache {
-    pub fn load() -> Result<Self> {
+    pub fn load(config: &Config) -> Result<Self> {
+        let key = Self::encryption_key()?;
         let path = Self::cache_path()?;
-        if path.exists() {
-            let content = std::fs::read_to_string(&path)
+        Self::load_from(&path, key, config)
+    }
+
+    /// Load the cache file at `path`, decrypting it with `key`
+    fn load_from(path: &Path, key: Key, config: &Config) -> Result<Self> {
+        let file: CacheFile = if path.exists() {
+            let content = std::fs::read_to_string(path)
                 .with_context(|| format!("Failed to read cache file: {:?}", path))?;
-            let cache: Cache = serde_json::from_str(&content).unwrap_or_default();
-            Ok(cache)
+            serde_json::from_str(&content).unwrap_or_default()
         } else {
-            Ok(Cache::default())
-        }
+            CacheFile::default()
+        };
+
+        // Entries that fail to decrypt (e.g. after a key rotation) are dropped,
+        // which turns them into cache misses
+        let entries = decrypt_entries(&key, file.entries);
+        let lists = decrypt_entries(&key, file.lists);
+
+        let ttl = Duration::seconds(config.cache_ttl_seconds.unwrap_or(DEFAULT_TTL_SECONDS));
+        let list_ttl = Duration::seconds(
+            config
+                .cache_list_ttl_seconds
+                .unwrap_or(DEFAULT_LIST_TTL_SECONDS),
+        );
+
+        let mut cache = Cache {
+            entries,
+            lists,
+            ttl,
+            list_ttl,
+            key,
+            config: config.clone(),
+        };
+        cache.prune_expired();
+        Ok(cache)
     }
 
-    pub fn save(&self) -> Result<()> {
+    pub fn save(&mut self) -> Result<()> {
         let path = Self::cache_path()?;
+        self.save_to(&path)
+    }
+
+    /// Write the cache, encrypted, to `path`
+    fn save_to(&mut self, path: &Path) -> Result<()> {
+        self.prune_expired();
+
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
-        let content = serde_json::to_string(&self)?;
+
+        let file = CacheFile {
+            entries: encrypt_entries(&self.key, &self.entries)?,
+            lists: encrypt_entries(&self.key, &self.lists)?,
+        };
+
+        let content = serde_json::to_string(&file)?;
         std::fs::write(path, content)?;
         Ok(())
     }
 
-    fn cache_path() -> Result<PathBuf> {
+    /// Location of the cache file
+    pub fn cache_path() -> Result<PathBuf> {
         if let Ok(path) = std::env::var("KSECRET_CACHE_FILE") {
             return Ok(PathBuf::from(path));
         }
@@ -50,8 +130,47 @@ impl Cache {
         Ok(config_dir.join(CACHE_FILE_NAME))
     }
 
+    /// Resolve the cache encryption key.
+    ///
+    /// `KSECRET_CACHE_KEY` takes precedence and is hashed into a key. Otherwise a
+    /// random key is stored in the OS keyring on first use.
+    fn encryption_key() -> Result<Key> {
+        if let Ok(passphrase) = std::env::var("KSECRET_CACHE_KEY") {
+            let digest = Sha256::digest(passphrase.as_bytes());
+            return Ok(*Key::from_slice(&digest));
+        }
+
+        let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_USER)
+            .context("Failed to open keyring entry for cache key")?;
+
+        match entry.get_password() {
+            Ok(encoded) => {
+                let bytes = BASE64
+                    .decode(encoded)
+                    .context("Cache key in keyring is not valid base64")?;
+                if bytes.len() != 32 {
+                    anyhow::bail!("Cache key in keyring has an invalid length");
+                }
+                Ok(*Key::from_slice(&bytes))
+            }
+            Err(keyring::Error::NoEntry) => {
+                let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+                entry
+                    .set_password(&BASE64.encode(key))
+                    .context("Failed to store cache key in keyring")?;
+                Ok(key)
+            }
+            Err(e) => Err(e).context("Failed to read cache key from keyring"),
+        }
+    }
+
+    #[allow(dead_code)]
     pub fn get(&self, env: &str, name: &str) -> Option<String> {
-        let key = format!("{}:{}", env, name);
+        self.get_version(env, name, "latest")
+    }
+
+    pub fn get_version(&self, env: &str, name: &str, version: &str) -> Option<String> {
+        let key = entry_key(&self.scope(env), env, name, version);
         if let Some(entry) = self.entries.get(&key) {
             if entry.expires_at > Utc::now() {
                 return Some(entry.value.clone());
@@ -61,22 +180,350 @@ impl Cache {
     }
 
     pub fn set(&mut self, env: &str, name: &str, value: String) {
-        let key = format!("{}:{}", env, name);
+        self.set_version(env, name, "latest", value);
+    }
+
+    pub fn set_version(&mut self, env: &str, name: &str, version: &str, value: String) {
+        let key = entry_key(&self.scope(env), env, name, version);
         self.entries.insert(
             key,
             CacheEntry {
                 value,
-                expires_at: Utc::now() + Duration::seconds(DEFAULT_TTL_SECONDS),
+                expires_at: Utc::now() + self.ttl,
+            },
+        );
+    }
+
+    /// Cached secret listing of an environment, if it hasn't expired
+    pub fn get_list(&self, env: &str) -> Option<Vec<SecretInfo>> {
+        let entry = self.lists.get(&list_key(&self.scope(env), env))?;
+        if entry.expires_at <= Utc::now() {
+            return None;
+        }
+        serde_json::from_str(&entry.value).ok()
+    }
+
+    pub fn set_list(&mut self, env: &str, secrets: &[SecretInfo]) -> Result<()> {
+        self.lists.insert(
+            list_key(&self.scope(env), env),
+            CacheEntry {
+                value: serde_json::to_string(secrets)?,
+                expires_at: Utc::now() + self.list_ttl,
             },
         );
+        Ok(())
     }
-    
+
+    /// Forget the cached listing of an environment after its secrets changed
+    pub fn invalidate_list(&mut self, env: &str) {
+        self.lists.remove(&list_key(&self.scope(env), env));
+    }
+
+    /// Remove all cached versions of a secret
     pub fn delete(&mut self, env: &str, name: &str) {
-        let key = format!("{}:{}", env, name);
-        self.entries.remove(&key);
+        let key = entry_key(&self.scope(env), env, name, "latest");
+        let version_prefix = format!("{}@", key);
+        self.entries
+            .retain(|k, _| k != &key && !k.starts_with(&version_prefix));
     }
-    
+
+    /// Drop entries that have expired so stale values don't stay on disk
+    pub fn prune_expired(&mut self) {
+        let now = Utc::now();
+        self.entries.retain(|_, entry| entry.expires_at > now);
+        self.lists.retain(|_, entry| entry.expires_at > now);
+    }
+
     pub fn clear(&mut self) {
         self.entries.clear();
+        self.lists.clear();
+    }
+
+    /// Remove all cached secrets of one environment, in every project and
+    /// under every prefix
+    pub fn clear_env(&mut self, env: &str) {
+        let other_env = |k: &String| k.split(':').nth(1) != Some(env);
+        self.entries.retain(|k, _| other_env(k));
+        self.lists.retain(|k, _| other_env(k));
+    }
+
+    /// Number of entries that are no longer served
+    pub fn expired_count(&self) -> usize {
+        let now = Utc::now();
+        self.entries
+            .values()
+            .chain(self.lists.values())
+            .filter(|entry| entry.expires_at <= now)
+            .count()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len() + self.lists.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.lists.is_empty()
+    }
+
+    /// Cache keys (`project/prefix:env:name`, `project/prefix:env:name@version`,
+    /// or `project/prefix:env:*` for a listing)
+    /// with their expiry, sorted by key. Values are deliberately not exposed.
+    pub fn keys(&self) -> Vec<(String, DateTime<Utc>)> {
+        let values = self.entries.iter().map(|(k, entry)| (k.clone(), entry));
+        let lists = self
+            .lists
+            .iter()
+            .map(|(key, entry)| (format!("{}:*", key), entry));
+        let mut keys: Vec<_> = values
+            .chain(lists)
+            .map(|(k, entry)| (k, entry.expires_at))
+            .collect();
+        keys.sort_by(|a, b| a.0.cmp(&b.0));
+        keys
+    }
+
+    /// Scope later lookups and updates by `config`'s project and prefix
+    /// instead, e.g. to reach secrets under another prefix
+    pub fn use_config(&mut self, config: &Config) {
+        self.config = config.clone();
+    }
+
+    /// `project/prefix` an environment's secrets live under
+    fn scope(&self, env: &str) -> String {
+        let resolved = self.config.for_environment(env);
+        format!("{}/{}", resolved.gcp_project_id, resolved.secret_prefix)
+    }
+}
+
+/// List an environment's secrets, serving and refreshing the cached listing.
+///
+/// `refresh` ignores a cached listing but still stores the new one. Without a
+/// usable cache (e.g. no keyring), this just lists from GCP.
+pub async fn list_secrets_cached(
+    config: &Config,
+    gcp_client: &SecretManagerClient,
+    env: &str,
+    refresh: bool,
+) -> Result<Vec<SecretInfo>> {
+    let mut cache = Cache::load(config).ok();
+    if !refresh {
+        if let Some(secrets) = cache.as_ref().and_then(|c| c.get_list(env)) {
+            tracing::debug!(env, count = secrets.len(), "Using cached secret list");
+            return Ok(secrets);
+        }
+    }
+
+    let secrets = gcp_client.list_secrets(env).await?;
+    if let Some(c) = cache.as_mut() {
+        if c.set_list(env, &secrets).is_ok() {
+            let _ = c.save();
+        }
+    }
+    Ok(secrets)
+}
+
+/// Decrypt loaded entries, dropping any that fail to decrypt
+fn decrypt_entries(key: &Key, entries: HashMap<String, CacheEntry>) -> HashMap<String, CacheEntry> {
+    entries
+        .into_iter()
+        .filter_map(|(k, entry)| {
+            let value = decrypt(key, &entry.value)?;
+            Some((
+                k,
+                CacheEntry {
+                    value,
+                    expires_at: entry.expires_at,
+                },
+            ))
+        })
+        .collect()
+}
+
+/// Encrypt entries for writing to disk
+fn encrypt_entries(
+    key: &Key,
+    entries: &HashMap<String, CacheEntry>,
+) -> Result<HashMap<String, CacheEntry>> {
+    entries
+        .iter()
+        .map(|(k, entry)| {
+            let encrypted = CacheEntry {
+                value: encrypt(key, &entry.value)?,
+                expires_at: entry.expires_at,
+            };
+            Ok((k.clone(), encrypted))
+        })
+        .collect()
+}
+
+/// Cache key for a secret version; "latest" keeps the plain
+/// `scope:env:name` form
+fn entry_key(scope: &str, env: &str, name: &str, version: &str) -> String {
+    if version == "latest" {
+        format!("{}:{}:{}", scope, env, name)
+    } else {
+        format!("{}:{}:{}@{}", scope, env, name, version)
+    }
+}
+
+/// Cache key for the secret listing of an environment
+fn list_key(scope: &str, env: &str) -> String {
+    format!("{}:{}", scope, env)
+}
+
+/// Encrypt a value, returning base64 of `nonce || ciphertext`
+fn encrypt(key: &Key, plaintext: &str) -> Result<String> {
+    let cipher = ChaCha20Poly1305::new(key);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_bytes())
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt cache entry"))?;
+
+    let mut blob = nonce.to_vec();
+    blob.extend_from_slice(&ciphertext);
+    Ok(BASE64.encode(blob))
+}
+
+/// Decrypt a value produced by [`encrypt`], returning `None` on any failure
+fn decrypt(key: &Key, encoded: &str) -> Option<String> {
+    let blob = BASE64.decode(encoded).ok()?;
+    if blob.len() < NONCE_LEN {
+        return None;
+    }
+
+    let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(key);
+    let plaintext = cipher.decrypt(Nonce::from_slice(nonce), ciphertext).ok()?;
+    String::from_utf8(plaintext).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EnvironmentConfig;
+
+    fn config(project: &str, prefix: &str) -> Config {
+        Config {
+            gcp_project_id: project.to_string(),
+            secret_prefix: prefix.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn cache_with_ttl(ttl: Duration) -> Cache {
+        Cache {
+            entries: HashMap::new(),
+            lists: HashMap::new(),
+            ttl,
+            list_ttl: ttl,
+            key: ChaCha20Poly1305::generate_key(&mut OsRng),
+            config: config("my-project", "k8s"),
+        }
+    }
+
+    #[test]
+    fn get_reports_entry_expired_after_ttl() {
+        let mut cache = cache_with_ttl(Duration::seconds(1));
+        cache.set("dev", "db-password", "hunter2".to_string());
+        assert_eq!(cache.get("dev", "db-password").as_deref(), Some("hunter2"));
+
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+
+        assert_eq!(cache.get("dev", "db-password"), None);
+        assert_eq!(cache.expired_count(), 1);
+    }
+
+    #[test]
+    fn saved_cache_file_has_no_plaintext_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CACHE_FILE_NAME);
+        let mut cache = cache_with_ttl(Duration::seconds(60));
+        cache.set("dev", "db-password", "hunter2-plaintext".to_string());
+        cache.set_version("dev", "db-password", "3", "older-plaintext".to_string());
+
+        cache.save_to(&path).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("my-project/k8s:dev:db-password"));
+        assert!(!content.contains("hunter2-plaintext"));
+        assert!(!content.contains("older-plaintext"));
+    }
+
+    #[test]
+    fn expired_entries_are_pruned_on_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CACHE_FILE_NAME);
+        let mut cache = cache_with_ttl(Duration::seconds(60));
+        cache.set("dev", "fresh", "value".to_string());
+        let stale = entry_key(&cache.scope("dev"), "dev", "stale", "latest");
+        cache.entries.insert(
+            stale.clone(),
+            CacheEntry {
+                value: "old".to_string(),
+                expires_at: Utc::now() - Duration::seconds(1),
+            },
+        );
+
+        cache.save_to(&path).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let file: CacheFile = serde_json::from_str(&content).unwrap();
+        assert_eq!(file.entries.len(), 1);
+        assert!(!file.entries.contains_key(&stale));
+        assert!(!content.contains("stale"));
+
+        let loaded = Cache::load_from(&path, cache.key, &cache.config).unwrap();
+        assert_eq!(loaded.get("dev", "fresh").as_deref(), Some("value"));
+        assert_eq!(loaded.len(), 1);
+    }
+
+    #[test]
+    fn entries_are_scoped_by_project_and_prefix() {
+        let mut cache = cache_with_ttl(Duration::seconds(60));
+        cache.set("dev", "db-password", "from-k8s".to_string());
+
+        cache.use_config(&config("other-project", "k8s"));
+        assert_eq!(cache.get("dev", "db-password"), None);
+
+        cache.use_config(&config("my-project", "legacy"));
+        assert_eq!(cache.get("dev", "db-password"), None);
+        cache.set("dev", "db-password", "from-legacy".to_string());
+
+        cache.use_config(&config("my-project", "k8s"));
+        assert_eq!(cache.get("dev", "db-password").as_deref(), Some("from-k8s"));
+    }
+
+    #[test]
+    fn lists_are_scoped_by_environment_overrides() {
+        let mut base = config("my-project", "k8s");
+        base.environments.insert(
+            "prod".to_string(),
+            EnvironmentConfig {
+                gcp_project_id: Some("prod-project".to_string()),
+                secret_prefix: None,
+            },
+        );
+        let mut cache = cache_with_ttl(Duration::seconds(60));
+        cache.use_config(&base);
+        cache.set_list("prod", &[]).unwrap();
+
+        assert_eq!(cache.keys()[0].0, "prod-project/k8s:prod:*");
+        cache.use_config(&config("my-project", "k8s"));
+        assert!(cache.get_list("prod").is_none());
+    }
+
+    #[test]
+    fn clear_env_clears_every_scope() {
+        let mut cache = cache_with_ttl(Duration::seconds(60));
+        cache.set("dev", "a", "1".to_string());
+        cache.set("prod", "a", "2".to_string());
+        cache.use_config(&config("my-project", "legacy"));
+        cache.set("dev", "a", "3".to_string());
+        cache.set_list("dev", &[]).unwrap();
+
+        cache.clear_env("dev");
+
+        let keys: Vec<_> = cache.keys().into_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["my-project/k8s:prod:a"]);
     }
 }
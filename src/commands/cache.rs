@@ -0,0 +1,87 @@
+use anyhow::Result;
+use chrono::Utc;
+use colored::Colorize;
+
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::output::status;
+
+/// Remove cached secrets, optionally only those of one environment
+pub fn clear(config: &Config, env: Option<&str>) -> Result<()> {
+    let mut cache = Cache::load(config)?;
+    let before = cache.len();
+
+    match env {
+        Some(env) => cache.clear_env(env),
+        None => cache.clear(),
+    }
+
+    let removed = before - cache.len();
+    cache.save()?;
+
+    match env {
+        Some(env) => status!(
+            "{} Removed {} cached entry(ies) for environment '{}'",
+            "OK".green().bold(),
+            removed,
+            env.cyan()
+        ),
+        None => status!(
+            "{} Removed {} cached entry(ies)",
+            "OK".green().bold(),
+            removed
+        ),
+    }
+
+    Ok(())
+}
+
+/// Show where the cache lives and how many entries it holds
+pub fn info(config: &Config) -> Result<()> {
+    let cache = Cache::load(config)?;
+    let path = Cache::cache_path()?;
+
+    println!("{} Cache information:\n", "->".blue().bold());
+    println!("  {:<10} {}", "Path:".bold(), path.display());
+    println!(
+        "  {:<10} {}",
+        "Entries:".bold(),
+        cache.len().to_string().green()
+    );
+    println!(
+        "  {:<10} {}",
+        "Expired:".bold(),
+        cache.expired_count().to_string().yellow()
+    );
+
+    Ok(())
+}
+
+/// List cached keys and their expiry, without printing any values
+pub fn list(config: &Config) -> Result<()> {
+    let cache = Cache::load(config)?;
+
+    if cache.is_empty() {
+        println!("{} The cache is empty", "!".yellow().bold());
+        return Ok(());
+    }
+
+    println!("{} Cached secrets:\n", "->".blue().bold());
+    println!("  {:<40} {:<24}", "KEY".bold(), "EXPIRES".bold());
+    println!("  {}", "-".repeat(64));
+
+    let now = Utc::now();
+    for (key, expires_at) in cache.keys() {
+        let expires = expires_at.format("%Y-%m-%d %H:%M:%S UTC").to_string();
+        let expires = if expires_at <= now {
+            format!("{} (expired)", expires).yellow()
+        } else {
+            expires.normal()
+        };
+        println!("  {:<40} {}", key, expires);
+    }
+
+    println!("\n  Total: {} entry(ies)", cache.len().to_string().green());
+
+    Ok(())
+}
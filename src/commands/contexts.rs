@@ -0,0 +1,43 @@
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::k8s::KubeClient;
+
+/// List the kubeconfig's contexts, marking the current one, so valid
+/// `--context` values are easy to find
+pub fn execute(output: &str) -> Result<()> {
+    let contexts = KubeClient::contexts()?;
+
+    match output {
+        "json" => println!("{}", serde_json::to_string_pretty(&contexts)?),
+        _ => {
+            if contexts.is_empty() {
+                println!("{} The kubeconfig defines no contexts", "!".yellow().bold());
+                return Ok(());
+            }
+
+            let name_width = contexts.iter().map(|c| c.name.len()).max().unwrap_or(0);
+            for context in &contexts {
+                let marker = if context.current { "*" } else { " " };
+                // Pad names only when a namespace column follows
+                let name = match &context.namespace {
+                    Some(_) => format!("{:<width$}", context.name, width = name_width),
+                    None => context.name.clone(),
+                };
+                let name = if context.current {
+                    name.green().bold()
+                } else {
+                    name.normal()
+                };
+                match &context.namespace {
+                    Some(namespace) => {
+                        println!("{} {}  {}", marker.green().bold(), name, namespace.dimmed())
+                    }
+                    None => println!("{} {}", marker.green().bold(), name),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
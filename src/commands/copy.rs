@@ -0,0 +1,254 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::BTreeMap;
+
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::gcp::client::SecretVersionInfo;
+use crate::gcp::SecretManagerClient;
+use crate::output::{status, status_inline};
+
+/// Options controlling a copy
+#[derive(Debug, Default)]
+pub struct CopyOptions {
+    /// Show what would be copied without making changes
+    pub dry_run: bool,
+    /// Overwrite secrets that already exist in the destination
+    pub overwrite: bool,
+    /// Copy into this GCP project instead of the configured one
+    pub to_project: Option<String>,
+    /// Copy every enabled version, oldest first, instead of only the latest
+    pub all_versions: bool,
+}
+
+pub async fn execute(
+    config: &Config,
+    name: Option<&str>,
+    from_env: &str,
+    to_env: &str,
+    options: CopyOptions,
+) -> Result<()> {
+    let CopyOptions {
+        dry_run,
+        overwrite,
+        to_project,
+        all_versions,
+    } = options;
+
+    // Each side uses its own environment's project and prefix
+    let source_config = config.for_environment(from_env).into_config();
+    let mut dest_config = config.for_environment(to_env).into_config();
+
+    // A different project makes copying within one environment meaningful
+    let to_project = to_project.filter(|p| *p != dest_config.gcp_project_id);
+    if from_env == to_env && to_project.is_none() {
+        anyhow::bail!("Source and destination environments must differ");
+    }
+    if let Some(project) = &to_project {
+        dest_config.gcp_project_id = project.clone();
+        // Already resolved; don't let the cache resolve the environment's
+        // own project again
+        dest_config.environments.remove(to_env);
+    }
+    let dest_project = dest_config.gcp_project_id.clone();
+
+    let gcp_client = SecretManagerClient::new(source_config).await?;
+    let dest = SecretManagerClient::new(dest_config.clone()).await?;
+
+    // Fail before copying anything if the destination can't be reached
+    dest.check_access()
+        .await
+        .with_context(|| format!("Can't access destination project '{}'", dest_project))?;
+
+    // Copy a single secret, or every secret in the source environment
+    let names: Vec<String> = match name {
+        Some(n) => vec![n.to_string()],
+        None => gcp_client
+            .list_secrets(from_env)
+            .await?
+            .into_iter()
+            .map(|s| s.name)
+            .collect(),
+    };
+
+    if names.is_empty() {
+        status!(
+            "{} No secrets found for environment '{}'",
+            "!".yellow().bold(),
+            from_env
+        );
+        return Ok(());
+    }
+
+    match to_project.as_deref() {
+        Some(project) => status!(
+            "{} Copying {} secret(s) from '{}' to '{}' in project '{}'",
+            "->".blue().bold(),
+            names.len().to_string().green(),
+            from_env.cyan(),
+            to_env.cyan(),
+            project.cyan()
+        ),
+        None => status!(
+            "{} Copying {} secret(s) from '{}' to '{}'",
+            "->".blue().bold(),
+            names.len().to_string().green(),
+            from_env.cyan(),
+            to_env.cyan()
+        ),
+    }
+
+    if dry_run {
+        status!("{}", "  (dry-run mode - no changes will be made)".yellow());
+    }
+
+    // New secrets get the replication settings from config, which may not
+    // suit the destination project
+    if to_project.is_some() && config.kms_key_name.is_some() {
+        status!(
+            "{} New secrets will be encrypted with the configured kms_key_name; the destination project's Secret Manager service agent needs access to it",
+            "!".yellow().bold()
+        );
+    }
+
+    let mut cache = Cache::load(&dest_config).ok();
+    let mut copied = 0;
+    let mut skipped = 0;
+
+    for secret_name in &names {
+        status_inline!("  {} {}... ", "->".blue(), secret_name);
+
+        if !overwrite && dest.secret_exists(to_env, secret_name).await? {
+            status!("{}", "skipped (exists, use --overwrite)".yellow());
+            skipped += 1;
+            continue;
+        }
+
+        if dry_run {
+            status!("{}", "would copy (dry-run)".yellow());
+            continue;
+        }
+
+        let versions: Vec<String> = if all_versions {
+            enabled_oldest_first(
+                gcp_client
+                    .list_secret_versions(from_env, secret_name)
+                    .await?,
+            )
+        } else {
+            vec!["latest".to_string()]
+        };
+
+        if versions.is_empty() {
+            status!("{}", "skipped (no enabled versions)".yellow());
+            skipped += 1;
+            continue;
+        }
+
+        let mut value = Vec::new();
+        for version in &versions {
+            value = gcp_client
+                .get_secret_version_bytes(from_env, secret_name, version)
+                .await
+                .with_context(|| format!("Failed to get secret: {}", secret_name))?;
+
+            dest.set_secret_bytes(
+                to_env,
+                secret_name,
+                &value,
+                &BTreeMap::new(),
+                &BTreeMap::new(),
+                None,
+            )
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to set secret '{}' in project '{}'",
+                    secret_name, dest_project
+                )
+            })?;
+        }
+
+        if let Some(c) = cache.as_mut() {
+            match String::from_utf8(value) {
+                Ok(text) => c.set(to_env, secret_name, text),
+                Err(_) => c.delete(to_env, secret_name),
+            }
+            c.invalidate_list(to_env);
+        }
+
+        if all_versions {
+            status!("{} ({} version(s))", "done".green(), versions.len());
+        } else {
+            status!("{}", "done".green());
+        }
+        copied += 1;
+    }
+
+    if let Some(c) = cache.as_mut() {
+        let _ = c.save();
+    }
+
+    if !dry_run {
+        status!(
+            "\n{} Copied {} secret(s), skipped {}",
+            "OK".green().bold(),
+            copied,
+            skipped
+        );
+    }
+
+    Ok(())
+}
+
+/// The enabled versions, oldest first, so copying them in order leaves the
+/// latest source version as the latest destination version
+fn enabled_oldest_first(versions: Vec<SecretVersionInfo>) -> Vec<String> {
+    let mut enabled: Vec<String> = versions
+        .into_iter()
+        .filter(|v| v.state == "ENABLED")
+        .map(|v| v.version)
+        .collect();
+
+    // Listing order isn't guaranteed, and "10" sorts before "9" as text
+    enabled.sort_by_key(|v| v.parse::<u64>().unwrap_or(0));
+    enabled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(number: u32, state: &str) -> SecretVersionInfo {
+        SecretVersionInfo {
+            version: number.to_string(),
+            state: state.to_string(),
+            created_at: None,
+            created_timestamp: None,
+            destroyed_at: None,
+            destroyed_timestamp: None,
+        }
+    }
+
+    #[test]
+    fn enabled_oldest_first_sorts_numerically() {
+        let versions = [9, 2, 10, 1, 11]
+            .into_iter()
+            .map(|n| version(n, "ENABLED"))
+            .collect();
+
+        assert_eq!(enabled_oldest_first(versions), ["1", "2", "9", "10", "11"]);
+    }
+
+    #[test]
+    fn enabled_oldest_first_skips_disabled_and_destroyed_versions() {
+        let versions = vec![
+            version(3, "ENABLED"),
+            version(2, "DISABLED"),
+            version(1, "DESTROYED"),
+            version(4, "ENABLED"),
+        ];
+
+        assert_eq!(enabled_oldest_first(versions), ["3", "4"]);
+    }
+}
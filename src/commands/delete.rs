@@ -1,12 +1,32 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::Colorize;
-use std::io::{self, Write};
+use regex::Regex;
+use std::io::{self, IsTerminal, Write};
 
 use crate::cache::Cache;
 use crate::config::Config;
 use crate::gcp::SecretManagerClient;
+use crate::output::{status, status_inline};
+
+pub async fn execute(
+    config: &Config,
+    name: &str,
+    env: &str,
+    force: bool,
+    dry_run: bool,
+) -> Result<()> {
+    config.validate_secret_id(env, name)?;
+
+    if dry_run {
+        status!(
+            "{} Would delete {}",
+            "->".blue().bold(),
+            config.build_resource_name(env, name).cyan()
+        );
+        status!("{}", "  (dry-run mode - no changes will be made)".yellow());
+        return Ok(());
+    }
 
-pub async fn execute(config: &Config, name: &str, env: &str, force: bool) -> Result<()> {
     if !force {
         print!(
             "{} Are you sure you want to delete secret '{}' from environment '{}'? [y/N] ",
@@ -20,7 +40,7 @@ pub async fn execute(config: &Config, name: &str, env: &str, force: bool) -> Res
         io::stdin().read_line(&mut input)?;
 
         if !input.trim().eq_ignore_ascii_case("y") {
-            println!("{}", "Aborted.".yellow());
+            status!("{}", "Aborted.".yellow());
             return Ok(());
         }
     }
@@ -29,12 +49,13 @@ pub async fn execute(config: &Config, name: &str, env: &str, force: bool) -> Res
     gcp_client.delete_secret(env, name).await?;
 
     // Update cache
-    if let Ok(mut cache) = Cache::load() {
+    if let Ok(mut cache) = Cache::load(config) {
         cache.delete(env, name);
+        cache.invalidate_list(env);
         let _ = cache.save();
     }
 
-    println!(
+    status!(
         "{} Secret '{}' deleted from environment '{}'",
         "OK".green().bold(),
         name.cyan(),
@@ -43,3 +64,132 @@ pub async fn execute(config: &Config, name: &str, env: &str, force: bool) -> Res
 
     Ok(())
 }
+
+/// Delete every secret of an environment, or only those whose name matches
+/// `pattern`.
+///
+/// The secrets are listed and confirmed once up front (unless `force`). A
+/// failing secret doesn't stop the rest; the command fails at the end instead.
+pub async fn execute_all(
+    config: &Config,
+    env: &str,
+    pattern: Option<&str>,
+    force: bool,
+    dry_run: bool,
+) -> Result<()> {
+    config.validate_environment(env)?;
+
+    let regex = pattern
+        .map(|pattern| {
+            Regex::new(pattern).with_context(|| format!("Invalid --regex '{}'", pattern))
+        })
+        .transpose()?;
+
+    let gcp_client = SecretManagerClient::new(config.clone()).await?;
+    let mut names: Vec<String> = gcp_client
+        .list_secrets(env)
+        .await?
+        .into_iter()
+        .map(|s| s.name)
+        .filter(|name| regex.as_ref().is_none_or(|re| re.is_match(name)))
+        .collect();
+    names.sort();
+
+    if names.is_empty() {
+        status!(
+            "{} No matching secrets found for environment '{}'",
+            "!".yellow().bold(),
+            env
+        );
+        return Ok(());
+    }
+
+    status!(
+        "{} {} secret(s) in environment '{}' will be deleted:",
+        "->".blue().bold(),
+        names.len().to_string().yellow(),
+        env.cyan()
+    );
+    for name in &names {
+        status!("    {}", name);
+    }
+
+    if dry_run {
+        status!("{}", "  (dry-run mode - no changes will be made)".yellow());
+        return Ok(());
+    }
+
+    if !force && !confirm_delete_all(env, names.len())? {
+        status!("{}", "Aborted.".yellow());
+        return Ok(());
+    }
+
+    let mut cache = Cache::load(config).ok();
+    let mut failed: Vec<(&str, anyhow::Error)> = Vec::new();
+    for name in &names {
+        status_inline!("  {} {}... ", "-".red(), name);
+        match gcp_client.delete_secret(env, name).await {
+            Ok(()) => {
+                if let Some(c) = cache.as_mut() {
+                    c.delete(env, name);
+                }
+                status!("{}", "deleted".red());
+            }
+            Err(e) => {
+                status!("{}", "failed".red());
+                failed.push((name, e));
+            }
+        }
+    }
+
+    if let Some(c) = cache.as_mut() {
+        c.invalidate_list(env);
+        let _ = c.save();
+    }
+
+    if !failed.is_empty() {
+        eprintln!(
+            "\n{} Deleted {} secret(s), {} failed:",
+            "!".red().bold(),
+            (names.len() - failed.len()).to_string().green(),
+            failed.len().to_string().red()
+        );
+        for (name, e) in &failed {
+            eprintln!("  {} {}: {:#}", "x".red(), name, e);
+        }
+        anyhow::bail!("{} secret(s) failed to delete", failed.len());
+    }
+
+    status!(
+        "\n{} Deleted {} secret(s) from environment '{}'",
+        "OK".green().bold(),
+        names.len(),
+        env.cyan()
+    );
+
+    Ok(())
+}
+
+/// Ask once before deleting many secrets. Without a terminal to ask on, the
+/// deletion is refused rather than reading an answer from piped input.
+fn confirm_delete_all(env: &str, count: usize) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        anyhow::bail!(
+            "Refusing to delete {} secret(s) without confirmation; pass --force to delete non-interactively",
+            count
+        );
+    }
+
+    print!(
+        "{} Delete these {} secret(s) from environment '{}'? [y/N] ",
+        "?".yellow().bold(),
+        count,
+        env.cyan()
+    );
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+
+    Ok(input.trim().eq_ignore_ascii_case("y"))
+}
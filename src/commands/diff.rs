@@ -0,0 +1,317 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use ring::hmac;
+use ring::rand::SystemRandom;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+use crate::commands::sync::{secret_data, NAMESPACE_LABEL};
+use crate::config::Config;
+use crate::gcp::SecretManagerClient;
+use crate::k8s::KubeTarget;
+
+/// Changes that a sync would make to a single Kubernetes secret
+#[derive(Debug, Serialize)]
+struct SecretDiff {
+    name: String,
+    status: &'static str,
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<String>,
+    /// Old and new value (or value hash) per key, when values are rendered
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    values: BTreeMap<String, ValueChange>,
+}
+
+/// Before and after rendering of a single key's value
+#[derive(Debug, Serialize)]
+struct ValueChange {
+    old: Option<String>,
+    new: Option<String>,
+}
+
+/// How much of the secret values a diff reveals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueDisplay {
+    /// Key names only
+    Hidden,
+    /// A short keyed hash of each value, only comparable within one run
+    Masked,
+    /// The full values
+    Shown,
+}
+
+/// Number of hex digits of the HMAC-SHA256 shown for a masked value
+const MASK_HASH_LEN: usize = 8;
+
+/// Key of the hash masked values are shown as. It's random per run, so a hash
+/// that ends up in a log can't be brute-forced back to its value.
+fn mask_key() -> &'static hmac::Key {
+    static KEY: OnceLock<hmac::Key> = OnceLock::new();
+    KEY.get_or_init(|| {
+        hmac::Key::generate(hmac::HMAC_SHA256, &SystemRandom::new())
+            .expect("system random number generator is unavailable")
+    })
+}
+
+impl ValueDisplay {
+    /// Values are only shown when asked for. Hashes are shown with `--mask`,
+    /// and by default in CI or when output isn't a terminal, where a diff is
+    /// likely to end up in logs
+    fn from_flags(mask: bool, show_values: bool) -> Self {
+        let in_ci = std::env::var_os("CI").is_some_and(|v| !v.is_empty());
+        if show_values {
+            ValueDisplay::Shown
+        } else if mask || in_ci || !std::io::stdout().is_terminal() {
+            ValueDisplay::Masked
+        } else {
+            ValueDisplay::Hidden
+        }
+    }
+
+    fn render(self, value: Option<&Vec<u8>>) -> Option<String> {
+        let value = value?;
+        match self {
+            ValueDisplay::Hidden => None,
+            ValueDisplay::Masked => {
+                let tag = hmac::sign(mask_key(), value);
+                let hash: String = tag.as_ref()[..MASK_HASH_LEN / 2]
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect();
+                Some(hash)
+            }
+            ValueDisplay::Shown => Some(format!("{:?}", String::from_utf8_lossy(value))),
+        }
+    }
+}
+
+pub async fn execute(
+    config: &Config,
+    env: &str,
+    namespace: Option<String>,
+    context: Option<String>,
+    output: &str,
+    mask: bool,
+    show_values: bool,
+) -> Result<()> {
+    let display = ValueDisplay::from_flags(mask, show_values);
+    let mut kube_target = KubeTarget::new(env, namespace, context);
+
+    let gcp_client = SecretManagerClient::new(config.clone())
+        .await
+        .context("Failed to initialize GCP client")?;
+
+    let k8s_client = kube_target.connect(config).await?;
+    let namespace = &kube_target.namespace;
+
+    let secrets = gcp_client.list_secrets(env).await?;
+
+    let mut diffs = Vec::new();
+    for secret_info in &secrets {
+        let version = config.pinned_version(env, &secret_info.name);
+        let value = gcp_client
+            .get_secret_version_bytes(env, &secret_info.name, version)
+            .await
+            .with_context(|| format!("Failed to get secret: {}", secret_info.name))?;
+
+        // Compare against exactly what sync would apply
+        let desired = secret_data(value);
+        let target = secret_info.labels.get(NAMESPACE_LABEL).unwrap_or(namespace);
+        let k8s_name = config.k8s_secret_name(&secret_info.name)?;
+        let current = k8s_client.get_secret(target, &k8s_name).await?;
+
+        diffs.push(diff_secret(
+            &secret_info.name,
+            &desired,
+            current.as_ref(),
+            display,
+        ));
+    }
+
+    match output {
+        "json" => {
+            let output = serde_json::json!({
+                "environment": env,
+                "namespace": namespace,
+                "secrets": diffs,
+            });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+        _ => print_diff(env, namespace, &diffs, display),
+    }
+
+    Ok(())
+}
+
+fn diff_secret(
+    name: &str,
+    desired: &BTreeMap<String, Vec<u8>>,
+    current: Option<&BTreeMap<String, Vec<u8>>>,
+    display: ValueDisplay,
+) -> SecretDiff {
+    let Some(current) = current else {
+        return SecretDiff {
+            name: name.to_string(),
+            status: "added",
+            added: desired.keys().cloned().collect(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+            values: value_changes(desired.keys(), desired, &BTreeMap::new(), display),
+        };
+    };
+
+    let added: Vec<String> = desired
+        .keys()
+        .filter(|k| !current.contains_key(*k))
+        .cloned()
+        .collect();
+    let removed: Vec<String> = current
+        .keys()
+        .filter(|k| !desired.contains_key(*k))
+        .cloned()
+        .collect();
+    let changed: Vec<String> = desired
+        .iter()
+        .filter(|(k, v)| current.get(*k).is_some_and(|c| c != *v))
+        .map(|(k, _)| k.clone())
+        .collect();
+
+    let status = if added.is_empty() && removed.is_empty() && changed.is_empty() {
+        "unchanged"
+    } else {
+        "changed"
+    };
+
+    let keys = added.iter().chain(&removed).chain(&changed);
+    let values = value_changes(keys, desired, current, display);
+
+    SecretDiff {
+        name: name.to_string(),
+        status,
+        added,
+        removed,
+        changed,
+        values,
+    }
+}
+
+/// Render the old and new values of `keys`, empty when values are hidden
+fn value_changes<'a>(
+    keys: impl Iterator<Item = &'a String>,
+    desired: &BTreeMap<String, Vec<u8>>,
+    current: &BTreeMap<String, Vec<u8>>,
+    display: ValueDisplay,
+) -> BTreeMap<String, ValueChange> {
+    if display == ValueDisplay::Hidden {
+        return BTreeMap::new();
+    }
+
+    keys.map(|key| {
+        let change = ValueChange {
+            old: display.render(current.get(key)),
+            new: display.render(desired.get(key)),
+        };
+        (key.clone(), change)
+    })
+    .collect()
+}
+
+fn print_diff(env: &str, namespace: &str, diffs: &[SecretDiff], display: ValueDisplay) {
+    println!(
+        "{} Diff for environment '{}' against namespace '{}'\n",
+        "->".blue().bold(),
+        env.cyan(),
+        namespace.cyan()
+    );
+
+    let mut unchanged = 0;
+    for diff in diffs {
+        match diff.status {
+            "unchanged" => {
+                unchanged += 1;
+                continue;
+            }
+            "added" => println!(
+                "  {} {} (new secret)",
+                "+".green().bold(),
+                diff.name.green()
+            ),
+            _ => println!("  {} {}", "~".yellow().bold(), diff.name.yellow()),
+        }
+
+        for key in &diff.added {
+            let line = format!("+ {}{}", key, value_suffix(diff, key));
+            println!("      {}", line.green());
+        }
+        for key in &diff.removed {
+            let line = format!("- {}{}", key, value_suffix(diff, key));
+            println!("      {}", line.red());
+        }
+        for key in &diff.changed {
+            let line = format!("~ {}{}", key, value_suffix(diff, key));
+            println!("      {}", line.yellow());
+        }
+    }
+
+    let pending = diffs.len() - unchanged;
+    if pending == 0 {
+        println!("  {} Namespace is up to date", "OK".green().bold());
+    } else {
+        println!(
+            "\n  {} secret(s) would change, {} unchanged",
+            pending.to_string().yellow(),
+            unchanged
+        );
+    }
+
+    if display == ValueDisplay::Masked && pending > 0 {
+        println!(
+            "  Values are shown as hashes that only match within this run; use {} to see them",
+            "--show-values".cyan()
+        );
+    }
+}
+
+/// The rendered value(s) of a key for text output, e.g. " (1a2b3c4d -> 5e6f7a8b)"
+fn value_suffix(diff: &SecretDiff, key: &str) -> String {
+    let Some(change) = diff.values.get(key) else {
+        return String::new();
+    };
+    match (&change.old, &change.new) {
+        (Some(old), Some(new)) => format!(" ({} -> {})", old, new),
+        (Some(value), None) | (None, Some(value)) => format!(" ({})", value),
+        (None, None) => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn masked_values_are_keyed_hashes() {
+        let value = b"hunter2".to_vec();
+
+        let masked = ValueDisplay::Masked.render(Some(&value)).unwrap();
+
+        assert_eq!(masked.len(), MASK_HASH_LEN);
+        assert_eq!(
+            ValueDisplay::Masked.render(Some(&value)),
+            Some(masked.clone())
+        );
+        let unsalted = format!("{:x}", Sha256::digest(&value));
+        assert_ne!(masked, unsalted[..MASK_HASH_LEN]);
+    }
+
+    #[test]
+    fn hidden_values_are_not_rendered() {
+        assert_eq!(
+            ValueDisplay::Hidden.render(Some(&b"hunter2".to_vec())),
+            None
+        );
+    }
+}
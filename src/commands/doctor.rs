@@ -0,0 +1,303 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use serde_json::json;
+use std::path::PathBuf;
+
+use super::status::{Check, Outcome};
+use crate::config::Config;
+use crate::error::{error_kind, ErrorKind};
+use crate::gcp::{identity, SecretManagerClient};
+use crate::k8s::KubeClient;
+use crate::timeout::TimeoutExt;
+
+/// Google endpoint whose `Date` header the local clock is compared against
+const CLOCK_CHECK_URL: &str = "https://secretmanager.googleapis.com/";
+/// Clock difference worth a warning: cache expiry and watch intervals drift
+const CLOCK_SKEW_WARNING_SECONDS: i64 = 60;
+/// Clock difference at which Google starts rejecting tokens
+const CLOCK_SKEW_CRITICAL_SECONDS: i64 = 300;
+
+/// Diagnose common setup problems and print a fix for each one found.
+///
+/// Every check runs (checks depending on a failed one are skipped) so all
+/// problems are reported at once. Fails if any critical check failed.
+pub async fn execute(config: Result<Config>, context: Option<&str>, output: &str) -> Result<()> {
+    let mut checks = Vec::new();
+
+    let config_check = check_config(&config);
+    let config_ok = config_check.is_ok();
+    checks.push(config_check);
+
+    // Credentials don't depend on the config, so check them either way
+    let base = config.as_ref().ok().cloned().unwrap_or_default();
+    let credentials_check = check_credentials(&base).await;
+    let credentials_ok = credentials_check.is_ok();
+    checks.push(credentials_check);
+
+    checks.push(match (config_ok, credentials_ok) {
+        (true, true) => check_project(&base).await,
+        (false, _) => Check::skipped("gcp project", "needs a valid config"),
+        (true, false) => Check::skipped("gcp project", "needs working credentials"),
+    });
+
+    checks.push(check_kubeconfig(&base, context));
+    checks.push(check_clock(&base).await);
+
+    let critical = checks
+        .iter()
+        .filter(|c| matches!(&c.outcome, Outcome::Failed(p) if p.critical))
+        .count();
+
+    match output {
+        "json" => {
+            let checks: Vec<_> = checks
+                .iter()
+                .map(|c| match &c.outcome {
+                    Outcome::Ok(detail) => {
+                        json!({ "name": c.name, "status": "ok", "detail": detail })
+                    }
+                    Outcome::Skipped(reason) => {
+                        json!({ "name": c.name, "status": "skipped", "detail": reason })
+                    }
+                    Outcome::Failed(p) => json!({
+                        "name": c.name,
+                        "status": if p.critical { "failed" } else { "warning" },
+                        "error": p.message,
+                        "fix": p.fix.as_deref().unwrap_or_default()
+                    }),
+                })
+                .collect();
+            let document = json!({ "checks": checks, "critical_failures": critical });
+            println!("{}", serde_json::to_string_pretty(&document)?);
+        }
+        _ => {
+            println!("{} ksecret doctor\n", "->".blue().bold());
+            for check in &checks {
+                check.print();
+            }
+        }
+    }
+
+    if critical > 0 {
+        anyhow::bail!("{} critical check(s) failed", critical);
+    }
+
+    Ok(())
+}
+
+/// The config file exists, parses, and names a project
+fn check_config(config: &Result<Config>) -> Check {
+    let name = "config";
+    let path = Config::config_path().ok();
+    let shown = path
+        .as_ref()
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| "the config file".to_string());
+
+    let config = match config {
+        Ok(config) => config,
+        Err(e) if path.as_ref().is_some_and(|p| !p.exists()) => {
+            return Check::failed(
+                name,
+                format!("{:#}", e),
+                "Run 'ksecret init --project <PROJECT_ID>'".to_string(),
+                true,
+            )
+        }
+        Err(e) => {
+            return Check::failed(
+                name,
+                format!("{:#}", e),
+                format!(
+                    "Fix or remove {}, or re-create it with 'ksecret init --force'",
+                    shown
+                ),
+                true,
+            )
+        }
+    };
+
+    if let Err(e) = config.validate() {
+        return Check::failed(
+            name,
+            format!("{:#}", e),
+            format!(
+                "Set gcp_project_id in {} (or pass --project), or re-run 'ksecret init --force'",
+                shown
+            ),
+            true,
+        );
+    }
+
+    Check::ok(
+        name,
+        format!(
+            "{} (project '{}', prefix '{}')",
+            shown, config.gcp_project_id, config.secret_prefix
+        ),
+    )
+}
+
+/// Application default credentials can be found and mint a token
+async fn check_credentials(config: &Config) -> Check {
+    let name = "credentials";
+    let source = adc_source();
+
+    match identity::access_token(config).await {
+        Ok(_) => Check::ok(
+            name,
+            match (&source, &config.impersonate_service_account) {
+                (Some(path), Some(email)) => format!("{}, impersonating {}", path.display(), email),
+                (Some(path), None) => path.display().to_string(),
+                (None, _) => "ambient credentials (metadata server)".to_string(),
+            },
+        ),
+        Err(e) => {
+            let fix = match (&source, &config.impersonate_service_account) {
+                (None, _) => "Run 'gcloud auth application-default login', or set GOOGLE_APPLICATION_CREDENTIALS to a service account key file".to_string(),
+                (Some(_), Some(email)) => format!(
+                    "Check that your account has roles/iam.serviceAccountTokenCreator on {}, or re-run 'gcloud auth application-default login'",
+                    email
+                ),
+                (Some(path), None) => format!(
+                    "The credentials in {} were rejected; re-run 'gcloud auth application-default login'",
+                    path.display()
+                ),
+            };
+            Check::failed(name, format!("{:#}", e), fix, true)
+        }
+    }
+}
+
+/// Credentials file the Google client libraries would use, if any:
+/// `GOOGLE_APPLICATION_CREDENTIALS`, then gcloud's well-known ADC file
+fn adc_source() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("GOOGLE_APPLICATION_CREDENTIALS") {
+        return Some(PathBuf::from(path));
+    }
+
+    let gcloud_dir = match std::env::var_os("CLOUDSDK_CONFIG") {
+        Some(dir) => PathBuf::from(dir),
+        None if cfg!(windows) => dirs::config_dir()?.join("gcloud"),
+        None => dirs::home_dir()?.join(".config").join("gcloud"),
+    };
+    let path = gcloud_dir.join("application_default_credentials.json");
+    path.is_file().then_some(path)
+}
+
+/// The project is reachable and the caller may list its secrets
+async fn check_project(config: &Config) -> Check {
+    let name = "gcp project";
+    let project = &config.gcp_project_id;
+
+    // One secret is enough to prove access; listing them all is slow in
+    // large projects
+    let checked = match SecretManagerClient::new(config.clone()).await {
+        Ok(client) => client.check_access().await,
+        Err(e) => Err(e),
+    };
+
+    match checked {
+        Ok(()) => Check::ok(name, format!("'{}' reachable", project)),
+        Err(e) => {
+            let fix = match error_kind(&e) {
+                ErrorKind::PermissionDenied => format!(
+                    "Grant your account read access, e.g.\n'gcloud projects add-iam-policy-binding {} --member=user:<EMAIL> --role=roles/secretmanager.viewer'\nand roles/secretmanager.secretAccessor to read secret values\n(and make sure the API is enabled: 'gcloud services enable secretmanager.googleapis.com --project {}')",
+                    project, project
+                ),
+                ErrorKind::NotFound => format!(
+                    "Check that project '{}' exists ('gcloud projects describe {}') and fix gcp_project_id",
+                    project, project
+                ),
+                ErrorKind::Unauthenticated => {
+                    "Run 'gcloud auth application-default login'".to_string()
+                }
+                ErrorKind::Other => format!(
+                    "Check your network connection, and that the Secret Manager API is enabled: 'gcloud services enable secretmanager.googleapis.com --project {}'",
+                    project
+                ),
+            };
+            Check::failed(name, format!("{:#}", e), fix, true)
+        }
+    }
+}
+
+/// The kubeconfig parses and has the context sync would use. Only `sync`,
+/// `diff`, `replicate` and `status` need Kubernetes, so this isn't critical.
+fn check_kubeconfig(config: &Config, context: Option<&str>) -> Check {
+    let name = "kubeconfig";
+    if config.in_cluster {
+        return Check::skipped(name, "in_cluster is set, the pod's service account is used");
+    }
+
+    let contexts = match KubeClient::contexts() {
+        Ok(contexts) => contexts,
+        Err(e) => {
+            return Check::failed(
+                name,
+                format!("{:#}", e),
+                "Set KUBECONFIG, or fetch credentials with 'gcloud container clusters get-credentials <CLUSTER>'".to_string(),
+                false,
+            )
+        }
+    };
+
+    let wanted = match context {
+        Some(context) => Some(context),
+        None => contexts.iter().find(|c| c.current).map(|c| c.name.as_str()),
+    };
+    match wanted {
+        Some(wanted) if contexts.iter().any(|c| c.name == wanted) => Check::ok(
+            name,
+            format!("{} context(s), using '{}'", contexts.len(), wanted),
+        ),
+        Some(wanted) => Check::failed(
+            name,
+            format!("Context '{}' is not in the kubeconfig", wanted),
+            "Run 'ksecret contexts' to see the available contexts".to_string(),
+            false,
+        ),
+        None => Check::failed(
+            name,
+            "The kubeconfig has no current context".to_string(),
+            "Run 'kubectl config use-context <CONTEXT>' or pass --context".to_string(),
+            false,
+        ),
+    }
+}
+
+/// The local clock agrees with Google's. Skew breaks cache expiry and, past a
+/// few minutes, token validation.
+async fn check_clock(config: &Config) -> Check {
+    let name = "clock";
+
+    let response = reqwest::Client::new()
+        .head(CLOCK_CHECK_URL)
+        .send()
+        .with_timeout(config.timeout())
+        .await;
+    let server_time = response.ok().and_then(|r| {
+        let date = r.headers().get(reqwest::header::DATE)?.to_str().ok()?;
+        DateTime::parse_from_rfc2822(date).ok()
+    });
+    let Some(server_time) = server_time else {
+        return Check::skipped(name, "couldn't get the time from Google to compare with");
+    };
+
+    let skew = (Utc::now() - server_time.with_timezone(&Utc)).num_seconds();
+    if skew.abs() < CLOCK_SKEW_WARNING_SECONDS {
+        return Check::ok(name, format!("within {}s of Google's", skew.abs().max(1)));
+    }
+
+    Check::failed(
+        name,
+        format!(
+            "Local clock is {}s {} Google's",
+            skew.abs(),
+            if skew > 0 { "ahead of" } else { "behind" }
+        ),
+        "Turn on network time sync (e.g. 'sudo timedatectl set-ntp true')".to_string(),
+        skew.abs() >= CLOCK_SKEW_CRITICAL_SECONDS,
+    )
+}
@@ -0,0 +1,48 @@
+use anyhow::Result;
+use std::collections::BTreeSet;
+
+use crate::config::Config;
+use crate::gcp::client::SecretInfo;
+use crate::gcp::SecretManagerClient;
+
+/// Secrets of every environment: those under the top-level project and
+/// prefix, plus those of environments configured with their own
+pub async fn list_all_secrets(config: &Config) -> Result<Vec<SecretInfo>> {
+    let overridden = config.overridden_environments();
+
+    let gcp_client = SecretManagerClient::new(config.clone()).await?;
+    let mut secrets: Vec<SecretInfo> = gcp_client
+        .list_all_secrets()
+        .await?
+        .into_iter()
+        .filter(|secret| !overridden.contains(&secret.environment))
+        .collect();
+
+    for environment in &overridden {
+        let env_client =
+            SecretManagerClient::new(config.for_environment(environment).into_config()).await?;
+        secrets.extend(env_client.list_secrets(environment).await?);
+    }
+
+    Ok(secrets)
+}
+
+/// Distinct environment names of the project's secrets, sorted
+pub async fn list_environments(config: &Config) -> Result<Vec<String>> {
+    let environments: BTreeSet<String> = list_all_secrets(config)
+        .await?
+        .into_iter()
+        .map(|secret| secret.environment)
+        .collect();
+
+    Ok(environments.into_iter().collect())
+}
+
+/// Print one environment name per line, for shell completion scripts
+pub async fn execute(config: &Config) -> Result<()> {
+    for environment in list_environments(config).await? {
+        println!("{}", environment);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::BTreeMap;
+
+use crate::commands::sync::parse_multi_value;
+use crate::config::Config;
+use crate::gcp::SecretManagerClient;
+use crate::output::status;
+
+pub async fn execute(config: &Config, env: &str, format: &str, output: Option<&str>) -> Result<()> {
+    if !matches!(format, "dotenv" | "json" | "yaml") {
+        anyhow::bail!(
+            "Unsupported export format '{}' (expected dotenv, json or yaml)",
+            format
+        );
+    }
+
+    let gcp_client = SecretManagerClient::new(config.clone()).await?;
+    let secrets = gcp_client.list_secrets(env).await?;
+
+    // Flatten multi-value secrets the same way sync does; single values are
+    // exported under the secret name
+    let mut values: BTreeMap<String, String> = BTreeMap::new();
+    for secret_info in &secrets {
+        let value = gcp_client
+            .get_secret(env, &secret_info.name)
+            .await
+            .with_context(|| format!("Failed to get secret: {}", secret_info.name))?;
+
+        match parse_multi_value(&value) {
+            Some(data) => {
+                for (k, v) in data {
+                    values.insert(k, String::from_utf8_lossy(&v).into_owned());
+                }
+            }
+            None => {
+                values.insert(env_var_name(&secret_info.name), value);
+            }
+        }
+    }
+
+    let content = match format {
+        "json" => format!("{}\n", serde_json::to_string_pretty(&values)?),
+        "yaml" => serde_yaml::to_string(&values)?,
+        _ => values
+            .iter()
+            .map(|(k, v)| format!("{}={}\n", k, dotenv_escape(v)))
+            .collect(),
+    };
+
+    match output {
+        Some(path) => {
+            std::fs::write(path, content)
+                .with_context(|| format!("Failed to write export file: {}", path))?;
+            status!(
+                "{} Exported {} value(s) from environment '{}' to {}",
+                "OK".green().bold(),
+                values.len(),
+                env.cyan(),
+                path
+            );
+        }
+        None => print!("{}", content),
+    }
+
+    Ok(())
+}
+
+/// Convert a secret name into a conventional environment variable name
+/// (e.g. `db-password` -> `DB_PASSWORD`)
+pub fn env_var_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Quote a value for a dotenv file when it contains anything beyond plain characters
+fn dotenv_escape(value: &str) -> String {
+    let is_plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:@%+,".contains(c));
+    if is_plain {
+        return value.to_string();
+    }
+
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '$' => escaped.push_str("\\$"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
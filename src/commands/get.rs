@@ -1,52 +1,138 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use colored::Colorize;
 use serde_json::json;
+use std::collections::BTreeMap;
 
 use crate::cache::Cache;
+use crate::commands::export::env_var_name;
+use crate::commands::sync::{parse_multi_value, secret_data};
 use crate::config::Config;
+use crate::error::{error_kind, ErrorKind};
 use crate::gcp::SecretManagerClient;
+use crate::output::print_document;
+
+/// How a `get` reads and prints the secret
+#[derive(Debug, Default)]
+pub struct GetOptions {
+    /// Output format (text, json, yaml, env)
+    pub output: String,
+    /// Version to read (a version number or "latest")
+    pub version: String,
+    /// Skip the cache and fetch directly from GCP
+    pub no_cache: bool,
+    /// Print each key a sync would write
+    pub all_keys: bool,
+    /// Fall back to the newest enabled version when `latest` is disabled
+    pub resolve_enabled: bool,
+    /// Print nothing and succeed when the secret doesn't exist
+    pub ignore_missing: bool,
+    /// Value printed instead when the secret doesn't exist
+    pub default: Option<String>,
+}
+
+pub async fn execute(config: &Config, name: &str, env: &str, options: GetOptions) -> Result<()> {
+    let GetOptions {
+        output,
+        version,
+        no_cache,
+        all_keys,
+        resolve_enabled,
+        ignore_missing,
+        default,
+    } = options;
+    let (output, version) = (output.as_str(), version.as_str());
+
+    config.validate_secret_id(env, name)?;
 
-pub async fn execute(
-    config: &Config,
-    name: &str,
-    env: &str,
-    output: &str,
-    no_cache: bool,
-) -> Result<()> {
     // Try to get from cache first
     let mut cache = if !no_cache {
-        Cache::load().ok()
+        Cache::load(config).ok()
     } else {
         None
     };
 
-    let value = if let Some(cached) = cache.as_ref().and_then(|c| c.get(env, name)) {
+    let cached = cache
+        .as_ref()
+        .and_then(|c| c.get_version(env, name, version));
+
+    let value = if let Some(cached) = cached {
         cached
     } else {
         let gcp_client = SecretManagerClient::new(config.clone()).await?;
-        let value = gcp_client.get_secret(env, name).await?;
-
-        // Update cache
-        if !no_cache {
-            if let Some(c) = cache.as_mut() {
-                c.set(env, name, value.clone());
-                let _ = c.save();
-            } else if let Ok(mut c) = Cache::load() {
-                // If cache failed to load earlier but we want to save now
-                c.set(env, name, value.clone());
-                let _ = c.save();
+        let fetched = gcp_client
+            .access_secret_version_resolving(env, name, version, resolve_enabled)
+            .await;
+        match fetched {
+            // Only a missing secret is expected; auth or network errors still fail
+            Err(e)
+                if error_kind(&e) == ErrorKind::NotFound
+                    && (default.is_some() || ignore_missing) =>
+            {
+                match default {
+                    // Printed like the value, but never cached
+                    Some(default) => default,
+                    None => return Ok(()),
+                }
+            }
+            fetched => {
+                let (resolved, data, fell_back) = fetched?;
+                let value = String::from_utf8(data).with_context(|| {
+                    format!(
+                        "Secret '{}' contains binary data that is not valid UTF-8",
+                        name
+                    )
+                })?;
+
+                if fell_back {
+                    eprintln!(
+                        "{} Latest version of '{}' is not enabled; using version {}",
+                        "!".yellow().bold(),
+                        name,
+                        resolved
+                    );
+                }
+
+                // Update cache; a fallback value isn't cached as "latest"
+                if !no_cache && !fell_back {
+                    if let Some(c) = cache.as_mut() {
+                        c.set_version(env, name, version, value.clone());
+                        let _ = c.save();
+                    } else if let Ok(mut c) = Cache::load(config) {
+                        // If cache failed to load earlier but we want to save now
+                        c.set_version(env, name, version, value.clone());
+                        let _ = c.save();
+                    }
+                }
+                value
             }
         }
-        value
     };
 
+    if all_keys {
+        return print_keys(name, env, version, &value, output);
+    }
+
     match output {
-        "json" => {
-            let output = json!({
+        "json" | "yaml" => {
+            let document = json!({
                 "name": name,
                 "environment": env,
+                "version": version,
                 "value": value
             });
-            println!("{}", serde_json::to_string_pretty(&output)?);
+            print_document(&document, output)?;
+        }
+        "env" => {
+            // Multi-value secrets are flattened the same way sync does
+            match parse_multi_value(&value) {
+                Some(data) => {
+                    for (k, v) in data {
+                        let v = String::from_utf8_lossy(&v);
+                        println!("{}={}", shell_var_name(&k), shell_quote(&v));
+                    }
+                }
+                None => println!("{}={}", env_var_name(name), shell_quote(&value)),
+            }
         }
         _ => {
             println!("{}", value);
@@ -55,3 +141,69 @@ pub async fn execute(
 
     Ok(())
 }
+
+/// Print every key sync would write for the secret, flattened the same way
+fn print_keys(name: &str, env: &str, version: &str, value: &str, output: &str) -> Result<()> {
+    let data: BTreeMap<String, String> = secret_data(value.as_bytes().to_vec())
+        .into_iter()
+        .map(|(k, v)| (k, String::from_utf8_lossy(&v).into_owned()))
+        .collect();
+
+    match output {
+        "json" | "yaml" => {
+            let document = json!({
+                "name": name,
+                "environment": env,
+                "version": version,
+                "keys": data
+            });
+            print_document(&document, output)?;
+        }
+        "env" => {
+            for (k, v) in &data {
+                println!("{}={}", shell_var_name(k), shell_quote(v));
+            }
+        }
+        _ => {
+            let width = data.keys().map(String::len).max().unwrap_or(0).max(3);
+            println!(
+                "{:<width$}  {}",
+                "KEY".bold(),
+                "VALUE".bold(),
+                width = width
+            );
+            for (k, v) in &data {
+                println!("{:<width$}  {}", k, v, width = width);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Turn a key into a valid shell variable name, replacing anything outside
+/// `[A-Za-z0-9_]` with `_`
+fn shell_var_name(key: &str) -> String {
+    let name: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+/// Single-quote a value for safe `eval` / `source`, unless it's plain
+fn shell_quote(value: &str) -> String {
+    let is_plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:@%+,".contains(c));
+    if is_plain {
+        return value.to_string();
+    }
+
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::config::Config;
+use crate::gcp::SecretManagerClient;
+use crate::output::status;
+
+/// Member types IAM accepts in a binding
+const MEMBER_TYPES: &[&str] = &[
+    "user",
+    "serviceAccount",
+    "group",
+    "domain",
+    "principal",
+    "principalSet",
+];
+
+/// Grant `role` on a secret to `member`
+pub async fn grant(config: &Config, name: &str, env: &str, member: &str, role: &str) -> Result<()> {
+    config.validate_secret_id(env, name)?;
+    validate_member(member)?;
+
+    let gcp_client = SecretManagerClient::new(config.clone()).await?;
+    let changed = gcp_client.add_iam_binding(env, name, member, role).await?;
+
+    if changed {
+        status!(
+            "{} Granted '{}' on secret '{}' to {}",
+            "OK".green().bold(),
+            role,
+            name.cyan(),
+            member.cyan()
+        );
+    } else {
+        status!(
+            "{} {} already has '{}' on secret '{}'",
+            "!".yellow().bold(),
+            member,
+            role,
+            name
+        );
+    }
+
+    Ok(())
+}
+
+/// Remove `member` from `role` on a secret
+pub async fn revoke(
+    config: &Config,
+    name: &str,
+    env: &str,
+    member: &str,
+    role: &str,
+) -> Result<()> {
+    config.validate_secret_id(env, name)?;
+    validate_member(member)?;
+
+    let gcp_client = SecretManagerClient::new(config.clone()).await?;
+    let changed = gcp_client
+        .remove_iam_binding(env, name, member, role)
+        .await?;
+
+    if changed {
+        status!(
+            "{} Revoked '{}' on secret '{}' from {}",
+            "OK".green().bold(),
+            role,
+            name.cyan(),
+            member.cyan()
+        );
+    } else {
+        status!(
+            "{} {} does not have '{}' on secret '{}'",
+            "!".yellow().bold(),
+            member,
+            role,
+            name
+        );
+    }
+
+    Ok(())
+}
+
+/// Check that a member has the `type:value` form IAM expects
+fn validate_member(member: &str) -> Result<()> {
+    match member.split_once(':') {
+        Some((kind, value)) if MEMBER_TYPES.contains(&kind) && !value.is_empty() => Ok(()),
+        _ => anyhow::bail!(
+            "Invalid member '{}': expected <type>:<id>, e.g. user:jane@example.com or serviceAccount:app@my-project.iam.gserviceaccount.com",
+            member
+        ),
+    }
+}
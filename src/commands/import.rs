@@ -0,0 +1,242 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::BTreeMap;
+
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::gcp::client::validate_secret_size;
+use crate::gcp::SecretManagerClient;
+use crate::output::{status, status_inline};
+
+pub async fn execute(
+    config: &Config,
+    env: &str,
+    file: &str,
+    format: &str,
+    name_prefix: Option<&str>,
+    dry_run: bool,
+) -> Result<()> {
+    let content = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read import file: {}", file))?;
+
+    let entries = match format {
+        "dotenv" => parse_dotenv(&content)?,
+        "json" => parse_json(&content)?,
+        _ => anyhow::bail!(
+            "Unsupported import format '{}' (expected dotenv or json)",
+            format
+        ),
+    };
+
+    if entries.is_empty() {
+        status!("{} No entries found in {}", "!".yellow().bold(), file);
+        return Ok(());
+    }
+
+    check_entries(config, env, &entries, name_prefix)?;
+
+    status!(
+        "{} Importing {} secret(s) into environment '{}'",
+        "->".blue().bold(),
+        entries.len().to_string().green(),
+        env.cyan()
+    );
+
+    if dry_run {
+        status!("{}", "  (dry-run mode - no changes will be made)".yellow());
+    }
+
+    let gcp_client = SecretManagerClient::new(config.clone()).await?;
+    let mut cache = Cache::load(config).ok();
+    let mut created = 0;
+    let mut updated = 0;
+
+    for (key, value) in entries {
+        let name = format!("{}{}", name_prefix.unwrap_or_default(), key);
+        let exists = gcp_client.secret_exists(env, &name).await?;
+        let action = if exists { "update" } else { "create" };
+
+        status_inline!("  {} {}... ", "->".blue(), name);
+
+        if dry_run {
+            status!("{}", format!("would {} (dry-run)", action).yellow());
+        } else {
+            gcp_client
+                .set_secret(env, &name, &value)
+                .await
+                .with_context(|| format!("Failed to set secret: {}", name))?;
+
+            if let Some(c) = cache.as_mut() {
+                c.set(env, &name, value);
+                c.invalidate_list(env);
+            }
+
+            status!("{}", format!("{}d", action).green());
+        }
+
+        if exists {
+            updated += 1;
+        } else {
+            created += 1;
+        }
+    }
+
+    if let Some(c) = cache.as_mut() {
+        let _ = c.save();
+    }
+
+    let verb = if dry_run { "Would import" } else { "Imported" };
+    status!(
+        "\n{} {} {} secret(s): {} created, {} updated",
+        "OK".green().bold(),
+        verb,
+        created + updated,
+        created,
+        updated
+    );
+
+    Ok(())
+}
+
+/// Reject invalid names and oversized values, so a bad entry fails the import
+/// before anything is written
+fn check_entries(
+    config: &Config,
+    env: &str,
+    entries: &BTreeMap<String, String>,
+    name_prefix: Option<&str>,
+) -> Result<()> {
+    for (key, value) in entries {
+        let name = format!("{}{}", name_prefix.unwrap_or_default(), key);
+        config.validate_secret_id(env, &name)?;
+        validate_secret_size(&name, value.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Parse a dotenv file into key/value pairs.
+///
+/// Supports `#` comments, an optional `export ` prefix, single-quoted literals
+/// and double-quoted values with `\n`, `\"`, `\\` and `\$` escapes.
+fn parse_dotenv(content: &str) -> Result<BTreeMap<String, String>> {
+    let mut entries = BTreeMap::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, raw) = line
+            .split_once('=')
+            .with_context(|| format!("Invalid dotenv line {}: expected KEY=VALUE", index + 1))?;
+
+        let key = key.trim();
+        if key.is_empty() {
+            anyhow::bail!("Invalid dotenv line {}: empty key", index + 1);
+        }
+
+        let value = parse_dotenv_value(raw.trim())
+            .with_context(|| format!("Invalid dotenv line {}", index + 1))?;
+        entries.insert(key.to_string(), value);
+    }
+
+    Ok(entries)
+}
+
+fn parse_dotenv_value(raw: &str) -> Result<String> {
+    if let Some(rest) = raw.strip_prefix('\'') {
+        let end = rest.find('\'').context("Unterminated single quote")?;
+        return Ok(rest[..end].to_string());
+    }
+
+    if let Some(rest) = raw.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok(value),
+                '\\' => match chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some(other) => value.push(other),
+                    None => break,
+                },
+                _ => value.push(c),
+            }
+        }
+        anyhow::bail!("Unterminated double quote");
+    }
+
+    // Unquoted values end at an inline comment
+    let value = match raw.find(" #") {
+        Some(pos) => &raw[..pos],
+        None => raw,
+    };
+    Ok(value.trim().to_string())
+}
+
+/// Parse a flat JSON object into key/value pairs
+fn parse_json(content: &str) -> Result<BTreeMap<String, String>> {
+    let value: serde_json::Value =
+        serde_json::from_str(content).context("Failed to parse JSON import file")?;
+
+    let serde_json::Value::Object(map) = value else {
+        anyhow::bail!("JSON import file must contain an object at the top level");
+    };
+
+    Ok(map
+        .into_iter()
+        .map(|(k, v)| {
+            let v_str = match v {
+                serde_json::Value::String(s) => s,
+                _ => v.to_string(),
+            };
+            (k, v_str)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gcp::client::MAX_SECRET_SIZE;
+
+    fn config() -> Config {
+        Config {
+            gcp_project_id: "my-project".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn check_entries_accepts_valid_file() {
+        let entries = parse_dotenv("DB_HOST=db\nexport DB_PASSWORD='s3cret'\n").unwrap();
+        assert!(check_entries(&config(), "dev", &entries, Some("app_")).is_ok());
+    }
+
+    #[test]
+    fn check_entries_rejects_invalid_key() {
+        let entries = parse_dotenv("DB_HOST=db\nDB.PASSWORD=s3cret\n").unwrap();
+
+        let err = check_entries(&config(), "dev", &entries, None).unwrap_err();
+        assert!(err.to_string().contains("DB.PASSWORD"), "{}", err);
+    }
+
+    #[test]
+    fn check_entries_validates_prefixed_name() {
+        let entries = parse_json(r#"{"password": "s3cret"}"#).unwrap();
+
+        assert!(check_entries(&config(), "dev", &entries, Some("db/")).is_err());
+    }
+
+    #[test]
+    fn check_entries_rejects_oversized_value() {
+        let mut entries = BTreeMap::new();
+        entries.insert("CERT".to_string(), "a".repeat(MAX_SECRET_SIZE + 1));
+
+        assert!(check_entries(&config(), "dev", &entries, None).is_err());
+    }
+}
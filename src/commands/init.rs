@@ -1,22 +1,71 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::Colorize;
+use std::io::{self, IsTerminal, Write};
 use crate::config::Config;
+use crate::output::status;
+
+pub async fn execute(project: Option<&str>, prefix: Option<&str>, force: bool) -> Result<()> {
+    let config_path = Config::config_path()?;
+    if config_path.exists() && !force {
+        anyhow::bail!(
+            "Configuration already exists at {} (use --force to overwrite it)",
+            config_path.display()
+        );
+    }
+
+    let defaults = Config::default();
+    let (project, prefix) = match project {
+        Some(project) => (
+            project.to_string(),
+            prefix.unwrap_or(&defaults.secret_prefix).to_string(),
+        ),
+        None if io::stdin().is_terminal() => {
+            let project = prompt("GCP Project ID", None)?;
+            let prefix = match prefix {
+                Some(prefix) => prefix.to_string(),
+                None => prompt("Secret prefix", Some(&defaults.secret_prefix))?,
+            };
+            (project, prefix)
+        }
+        None => anyhow::bail!("--project is required when not running interactively"),
+    };
 
-pub async fn execute(project: &str) -> Result<()> {
     let config = Config {
-        gcp_project_id: project.to_string(),
-        secret_prefix: "k8s".to_string(),
+        gcp_project_id: project,
+        secret_prefix: prefix,
+        ..defaults
     };
+    config.validate()?;
 
     config.save()?;
 
-    let config_path = Config::config_path()?;
-    println!(
+    status!(
         "{} Configuration saved to {}",
         "✓".green().bold(),
         config_path.display()
     );
-    println!("  GCP Project ID: {}", project.cyan());
+    status!("  GCP Project ID: {}", config.gcp_project_id.cyan());
+    status!("  Secret prefix: {}", config.secret_prefix.cyan());
 
     Ok(())
 }
+
+/// Ask for a value on the terminal, falling back to `default` on empty input
+fn prompt(label: &str, default: Option<&str>) -> Result<String> {
+    match default {
+        Some(default) => print!("{} [{}]: ", label, default),
+        None => print!("{}: ", label),
+    }
+    io::stdout().flush().context("Failed to flush stdout")?;
+
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .context("Failed to read input")?;
+
+    let input = input.trim();
+    Ok(match default {
+        Some(default) if input.is_empty() => default.to_string(),
+        _ => input.to_string(),
+    })
+}
@@ -1,27 +1,127 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
 use colored::Colorize;
+use regex::Regex;
 use serde_json::json;
+use std::collections::BTreeMap;
 
+use crate::cache::list_secrets_cached;
+use crate::commands::environments::list_all_secrets;
+use crate::commands::set::parse_duration;
 use crate::config::Config;
+use crate::gcp::client::SecretInfo;
+use crate::gcp::labels::parse_labels;
 use crate::gcp::SecretManagerClient;
+use crate::output::print_document;
 
-pub async fn execute(config: &Config, env: &str, output: &str) -> Result<()> {
-    let gcp_client = SecretManagerClient::new(config.clone()).await?;
-    let secrets = gcp_client.list_secrets(env).await?;
+/// Widest the NAME column of the table grows
+const MAX_NAME_WIDTH: usize = 60;
+
+/// How a `list` filters, orders and prints secrets
+#[derive(Debug, Default)]
+pub struct ListOptions {
+    /// Output format (table, json, yaml)
+    pub output: String,
+    /// `key=value` labels every listed secret must have
+    pub label_selector: Vec<String>,
+    /// Regular expression secret names must match
+    pub regex: Option<String>,
+    /// Show a LABELS column in table output
+    pub show_labels: bool,
+    /// Sort by "name" or "created"
+    pub sort: String,
+    /// Reverse the sort order
+    pub reverse: bool,
+    /// Print only the names, one per line
+    pub names_only: bool,
+    /// Only list secrets created at or after this time: a duration ago
+    /// (e.g. `7d`) or a UTC date / RFC 3339 timestamp
+    pub since: Option<String>,
+    /// Always list from GCP, ignoring and not updating the cached listing
+    pub no_cache: bool,
+    /// List from GCP and replace the cached listing
+    pub refresh: bool,
+}
+
+/// List the secrets of one environment, or of every environment when `env`
+/// is `None`
+pub async fn execute(config: &Config, env: Option<&str>, options: ListOptions) -> Result<()> {
+    let ListOptions {
+        output,
+        label_selector,
+        regex,
+        show_labels,
+        sort,
+        reverse,
+        names_only,
+        since,
+        no_cache,
+        refresh,
+    } = options;
+    let output = output.as_str();
+
+    let selector = parse_labels(&label_selector)?;
+    let regex = regex
+        .as_deref()
+        .map(|pattern| {
+            Regex::new(pattern).with_context(|| format!("Invalid --regex '{}'", pattern))
+        })
+        .transpose()?;
+    let since = since.as_deref().map(parse_since).transpose()?;
+
+    let listed = match env {
+        Some(env) => {
+            let config = config.for_environment(env).into_config();
+            let gcp_client = SecretManagerClient::new(config.clone()).await?;
+            if no_cache {
+                gcp_client.list_secrets(env).await?
+            } else {
+                list_secrets_cached(&config, &gcp_client, env, refresh).await?
+            }
+        }
+        None => list_all_secrets(config).await?,
+    };
+    let mut secrets: Vec<_> = listed
+        .into_iter()
+        .filter(|s| selector.iter().all(|(k, v)| s.labels.get(k) == Some(v)))
+        .filter(|s| regex.as_ref().is_none_or(|re| re.is_match(&s.name)))
+        .filter(|s| match since {
+            Some(since) => s
+                .created_timestamp
+                .is_some_and(|created| created >= since.timestamp()),
+            None => true,
+        })
+        .collect();
+
+    // Sort before rendering so every output format shows the same order
+    match sort.as_str() {
+        "created" => secrets.sort_by(|a, b| {
+            a.created_timestamp
+                .cmp(&b.created_timestamp)
+                .then_with(|| a.name.cmp(&b.name))
+        }),
+        _ => secrets.sort_by(|a, b| a.name.cmp(&b.name)),
+    }
+    if reverse {
+        secrets.reverse();
+    }
+
+    // Plain names for shell loops: no headers, colors or empty-result notice
+    if names_only {
+        for secret in &secrets {
+            println!("{}", secret.name);
+        }
+        return Ok(());
+    }
+
+    let Some(env) = env else {
+        return print_all_environments(secrets, output, show_labels);
+    };
 
     match output {
-        "json" => {
-            let output: Vec<_> = secrets
-                .iter()
-                .map(|s| {
-                    json!({
-                        "name": s.name,
-                        "environment": s.environment,
-                        "created_at": s.created_at
-                    })
-                })
-                .collect();
-            println!("{}", serde_json::to_string_pretty(&output)?);
+        "json" | "yaml" => {
+            let document: Vec<_> = secrets.iter().map(secret_json).collect();
+            print_document(&document, output)?;
         }
         _ => {
             if secrets.is_empty() {
@@ -39,19 +139,7 @@ pub async fn execute(config: &Config, env: &str, output: &str) -> Result<()> {
                 env.cyan()
             );
 
-            println!("  {:<30} {:<20}", "NAME".bold(), "CREATED".bold());
-            println!("  {}", "-".repeat(50));
-
-            for secret in &secrets {
-                let created = secret
-                    .created_at
-                    .as_deref()
-                    .unwrap_or("-")
-                    .chars()
-                    .take(19)
-                    .collect::<String>();
-                println!("  {:<30} {:<20}", secret.name, created);
-            }
+            print_table(&secrets, show_labels);
 
             println!("\n  Total: {} secret(s)", secrets.len().to_string().green());
         }
@@ -59,3 +147,143 @@ pub async fn execute(config: &Config, env: &str, output: &str) -> Result<()> {
 
     Ok(())
 }
+
+/// Render secrets grouped by environment
+fn print_all_environments(secrets: Vec<SecretInfo>, output: &str, show_labels: bool) -> Result<()> {
+    let total = secrets.len();
+    let mut by_env: BTreeMap<String, Vec<SecretInfo>> = BTreeMap::new();
+    for secret in secrets {
+        by_env
+            .entry(secret.environment.clone())
+            .or_default()
+            .push(secret);
+    }
+
+    match output {
+        "json" | "yaml" => {
+            let document: BTreeMap<_, Vec<_>> = by_env
+                .iter()
+                .map(|(env, secrets)| (env, secrets.iter().map(secret_json).collect()))
+                .collect();
+            print_document(&document, output)?;
+        }
+        _ => {
+            if by_env.is_empty() {
+                println!("{} No secrets found", "!".yellow().bold());
+                return Ok(());
+            }
+
+            for (env, secrets) in &by_env {
+                println!(
+                    "{} Secrets for environment '{}':\n",
+                    "->".blue().bold(),
+                    env.cyan()
+                );
+                print_table(secrets, show_labels);
+                println!();
+            }
+
+            println!(
+                "  Total: {} secret(s) in {} environment(s)",
+                total.to_string().green(),
+                by_env.len().to_string().green()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn secret_json(secret: &SecretInfo) -> serde_json::Value {
+    json!({
+        "name": secret.name,
+        "environment": secret.environment,
+        "created_at": secret.created_at,
+        "expire_time": secret.expire_time,
+        "labels": secret.labels,
+        "annotations": secret.annotations
+    })
+}
+
+/// Print secrets as a table; an EXPIRES column is added when any secret has
+/// an expiration.
+///
+/// The NAME column is as wide as the longest name, up to
+/// `MAX_NAME_WIDTH`; longer names overflow rather than being cut off.
+fn print_table(secrets: &[SecretInfo], show_labels: bool) {
+    let show_expiry = secrets.iter().any(|s| s.expire_time.is_some());
+
+    let name_width = secrets
+        .iter()
+        .map(|s| s.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .clamp("NAME".len(), MAX_NAME_WIDTH);
+
+    let mut header = format!(
+        "  {:<name_width$} {:<20}",
+        "NAME".bold(),
+        "CREATED".bold(),
+        name_width = name_width
+    );
+    let mut width = name_width + 21;
+    if show_expiry {
+        header.push_str(&format!(" {:<20}", "EXPIRES".bold()));
+        width += 21;
+    }
+    if show_labels {
+        header.push_str(&format!(" {}", "LABELS".bold()));
+        width += 20;
+    }
+    println!("{}", header);
+    println!("  {}", "-".repeat(width));
+
+    for secret in secrets {
+        let mut row = format!(
+            "  {:<name_width$} {:<20}",
+            secret.name,
+            short_timestamp(secret.created_at.as_deref()),
+            name_width = name_width
+        );
+        if show_expiry {
+            row.push_str(&format!(
+                " {:<20}",
+                short_timestamp(secret.expire_time.as_deref())
+            ));
+        }
+        if show_labels {
+            let labels = secret
+                .labels
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v))
+                .collect::<Vec<_>>()
+                .join(",");
+            row.push_str(&format!(" {}", labels));
+        }
+        println!("{}", row.trim_end());
+    }
+}
+
+/// Parse a `--since` value: a duration before now (`7d`, `12h`), a date
+/// (`2024-01-01`, midnight UTC) or an RFC 3339 timestamp
+fn parse_since(text: &str) -> Result<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    let duration = parse_duration(text).map_err(|_| {
+        anyhow::anyhow!(
+            "Invalid --since '{}': expected a duration (e.g. 7d), a date (2024-01-01) or an RFC 3339 timestamp",
+            text
+        )
+    })?;
+    Ok(Utc::now() - chrono::Duration::from_std(duration)?)
+}
+
+/// Timestamp without the timezone suffix, or "-" when unset
+fn short_timestamp(timestamp: Option<&str>) -> String {
+    timestamp.unwrap_or("-").chars().take(19).collect()
+}
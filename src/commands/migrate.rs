@@ -0,0 +1,244 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::BTreeMap;
+
+use crate::cache::Cache;
+use crate::commands::environments::list_all_secrets;
+use crate::config::Config;
+use crate::gcp::client::SecretInfo;
+use crate::gcp::SecretManagerClient;
+use crate::output::{status, status_inline};
+
+/// Options controlling a migration
+#[derive(Debug, Default)]
+pub struct MigrateOptions {
+    /// Delete each original once its copy is confirmed
+    pub delete_source: bool,
+    /// Show what would be migrated without making changes
+    pub dry_run: bool,
+    /// Replace secrets that already exist under the new prefix
+    pub overwrite: bool,
+}
+
+/// Recreate the secrets of one environment (or of all, when `env` is `None`)
+/// under a new prefix, with their latest value, labels and annotations.
+///
+/// A failing secret doesn't stop the rest; the command fails at the end
+/// instead.
+pub async fn execute(
+    config: &Config,
+    from_prefix: &str,
+    to_prefix: &str,
+    env: Option<&str>,
+    options: MigrateOptions,
+) -> Result<()> {
+    let MigrateOptions {
+        delete_source,
+        dry_run,
+        overwrite,
+    } = options;
+
+    Config::validate_prefix(from_prefix)?;
+    Config::validate_prefix(to_prefix)?;
+    if from_prefix == to_prefix {
+        anyhow::bail!("Source and destination prefixes must differ");
+    }
+    if let Some(env) = env {
+        config.validate_environment(env)?;
+    }
+
+    // The explicit prefixes replace any per-environment ones; projects still
+    // come from each environment's config
+    let source_config = with_prefix(config, from_prefix);
+    let dest_config = with_prefix(config, to_prefix);
+
+    let secrets: Vec<SecretInfo> = match env {
+        Some(env) => {
+            let gcp_client =
+                SecretManagerClient::new(source_config.for_environment(env).into_config()).await?;
+            gcp_client.list_secrets(env).await?
+        }
+        None => list_all_secrets(&source_config).await?,
+    };
+
+    let mut by_environment: BTreeMap<String, Vec<SecretInfo>> = BTreeMap::new();
+    for secret in secrets {
+        by_environment
+            .entry(secret.environment.clone())
+            .or_default()
+            .push(secret);
+    }
+
+    let total: usize = by_environment.values().map(Vec::len).sum();
+    if total == 0 {
+        status!(
+            "{} No secrets found under prefix '{}'",
+            "!".yellow().bold(),
+            from_prefix
+        );
+        return Ok(());
+    }
+
+    status!(
+        "{} Migrating {} secret(s) from prefix '{}' to '{}'",
+        "->".blue().bold(),
+        total.to_string().green(),
+        from_prefix.cyan(),
+        to_prefix.cyan()
+    );
+    if dry_run {
+        status!("{}", "  (dry-run mode - no changes will be made)".yellow());
+    }
+
+    let mut cache = Cache::load(config).ok();
+    let mut migrated = 0;
+    let mut skipped = 0;
+    let mut failed: Vec<(String, anyhow::Error)> = Vec::new();
+
+    for (environment, secrets) in &mut by_environment {
+        secrets.sort_by(|a, b| a.name.cmp(&b.name));
+        let source =
+            SecretManagerClient::new(source_config.for_environment(environment).into_config())
+                .await?;
+        let dest = SecretManagerClient::new(dest_config.for_environment(environment).into_config())
+            .await?;
+
+        for secret in secrets.iter() {
+            let label = format!("{}/{}", environment, secret.name);
+            status_inline!("  {} {}... ", "->".blue(), label);
+
+            if !overwrite {
+                match dest.secret_exists(environment, &secret.name).await {
+                    Ok(false) => {}
+                    Ok(true) => {
+                        status!("{}", "skipped (exists, use --overwrite)".yellow());
+                        skipped += 1;
+                        continue;
+                    }
+                    Err(e) => {
+                        status!("{}", "failed".red());
+                        failed.push((label, e));
+                        continue;
+                    }
+                }
+            }
+
+            if dry_run {
+                status!("{}", "would migrate (dry-run)".yellow());
+                continue;
+            }
+
+            match migrate_secret(&source, &dest, secret, delete_source).await {
+                Ok(()) => {
+                    if delete_source {
+                        status!("{}", "moved".green());
+                    } else {
+                        status!("{}", "copied".green());
+                    }
+                    migrated += 1;
+                }
+                Err(e) => {
+                    status!("{}", "failed".red());
+                    failed.push((label, e));
+                }
+            }
+        }
+
+        if let Some(c) = cache.as_mut() {
+            if !dry_run {
+                let changed = if delete_source {
+                    vec![&source_config, &dest_config]
+                } else {
+                    vec![&dest_config]
+                };
+                for scope in changed {
+                    c.use_config(scope);
+                    for secret in secrets.iter() {
+                        c.delete(environment, &secret.name);
+                    }
+                    c.invalidate_list(environment);
+                }
+            }
+        }
+    }
+
+    if let Some(c) = cache.as_mut() {
+        let _ = c.save();
+    }
+
+    if !failed.is_empty() {
+        eprintln!(
+            "\n{} Migrated {} secret(s), skipped {}, {} failed:",
+            "!".red().bold(),
+            migrated.to_string().green(),
+            skipped,
+            failed.len().to_string().red()
+        );
+        for (label, e) in &failed {
+            eprintln!("  {} {}: {:#}", "x".red(), label, e);
+        }
+        anyhow::bail!("{} secret(s) failed to migrate", failed.len());
+    }
+
+    if !dry_run {
+        status!(
+            "\n{} Migrated {} secret(s), skipped {}",
+            "OK".green().bold(),
+            migrated,
+            skipped
+        );
+    }
+
+    Ok(())
+}
+
+/// `config` with every environment's secrets under `prefix`
+fn with_prefix(config: &Config, prefix: &str) -> Config {
+    let mut config = config.clone();
+    config.secret_prefix = prefix.to_string();
+    for overrides in config.environments.values_mut() {
+        overrides.secret_prefix = None;
+    }
+    config
+}
+
+/// Copy one secret's latest value to the destination prefix, then delete the
+/// original if asked and the copy is confirmed
+async fn migrate_secret(
+    source: &SecretManagerClient,
+    dest: &SecretManagerClient,
+    secret: &SecretInfo,
+    delete_source: bool,
+) -> Result<()> {
+    let environment = &secret.environment;
+    let name = &secret.name;
+
+    let value = source
+        .get_secret_bytes(environment, name)
+        .await
+        .with_context(|| format!("Failed to get secret: {}", name))?;
+
+    dest.set_secret_bytes(
+        environment,
+        name,
+        &value,
+        &secret.labels,
+        &secret.annotations,
+        None,
+    )
+    .await
+    .with_context(|| format!("Failed to create secret: {}", name))?;
+
+    if delete_source {
+        // Only remove the original once the copy is confirmed to exist
+        if !dest.secret_exists(environment, name).await? {
+            anyhow::bail!(
+                "Secret '{}' could not be confirmed under the new prefix; the original was left in place",
+                name
+            );
+        }
+        source.delete_secret(environment, name).await?;
+    }
+
+    Ok(())
+}
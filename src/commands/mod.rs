@@ -4,3 +4,20 @@ pub mod set;
 pub mod list;
 pub mod delete;
 pub mod init;
+pub mod copy;
+pub mod export;
+pub mod import;
+pub mod versions;
+pub mod rollback;
+pub mod diff;
+pub mod rename;
+pub mod cache;
+pub mod status;
+pub mod iam;
+pub mod audit;
+pub mod environments;
+pub mod whoami;
+pub mod replicate;
+pub mod migrate;
+pub mod contexts;
+pub mod doctor;
@@ -0,0 +1,100 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::gcp::SecretManagerClient;
+use crate::output::status;
+
+pub async fn execute(
+    config: &Config,
+    old_name: &str,
+    new_name: &str,
+    env: &str,
+    keep_old: bool,
+    overwrite: bool,
+) -> Result<()> {
+    if old_name == new_name {
+        anyhow::bail!("Old and new secret names must differ");
+    }
+    config.validate_secret_id(env, old_name)?;
+    config.validate_secret_id(env, new_name)?;
+
+    let gcp_client = SecretManagerClient::new(config.clone()).await?;
+
+    if !overwrite && gcp_client.secret_exists(env, new_name).await? {
+        anyhow::bail!(
+            "Secret '{}' already exists in environment '{}' (use --overwrite to replace it)",
+            new_name,
+            env
+        );
+    }
+
+    status!(
+        "{} Renaming secret '{}' to '{}' in environment '{}'",
+        "->".blue().bold(),
+        old_name.cyan(),
+        new_name.cyan(),
+        env.cyan()
+    );
+
+    // Secret Manager can't rename in place, so copy the latest value and the
+    // metadata over
+    let value = gcp_client
+        .get_secret_bytes(env, old_name)
+        .await
+        .with_context(|| format!("Failed to get secret: {}", old_name))?;
+    let info = gcp_client
+        .get_secret_info(env, old_name)
+        .await?
+        .with_context(|| format!("Secret '{}' was deleted during the rename", old_name))?;
+
+    gcp_client
+        .set_secret_bytes(env, new_name, &value, &info.labels, &info.annotations, None)
+        .await
+        .with_context(|| format!("Failed to create secret: {}", new_name))?;
+
+    // Only remove the old secret once the new one is confirmed to exist
+    if !gcp_client.secret_exists(env, new_name).await? {
+        anyhow::bail!(
+            "Secret '{}' could not be confirmed after creation; '{}' was left in place",
+            new_name,
+            old_name
+        );
+    }
+
+    if !keep_old {
+        gcp_client.delete_secret(env, old_name).await?;
+    }
+
+    // Update cache
+    if let Ok(mut cache) = Cache::load(config) {
+        if !keep_old {
+            cache.delete(env, old_name);
+        }
+        cache.delete(env, new_name);
+        if let Ok(text) = String::from_utf8(value) {
+            cache.set(env, new_name, text);
+        }
+        cache.invalidate_list(env);
+        let _ = cache.save();
+    }
+
+    if keep_old {
+        status!(
+            "{} Secret '{}' copied to '{}' (old secret kept)",
+            "OK".green().bold(),
+            old_name.cyan(),
+            new_name.cyan()
+        );
+    } else {
+        status!(
+            "{} Secret '{}' renamed to '{}'",
+            "OK".green().bold(),
+            old_name.cyan(),
+            new_name.cyan()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::commands::sync::{self, SyncOptions, DEFAULT_CONCURRENCY};
+use crate::config::Config;
+use crate::output::status;
+
+/// Sync an environment to the same namespace in several kube contexts,
+/// fetching the secrets from GCP only once.
+///
+/// A failing context doesn't stop the others; the command fails afterwards if
+/// any of them did.
+pub async fn execute(
+    config: &Config,
+    environment: &str,
+    namespace: Option<String>,
+    contexts: &[String],
+    dry_run: bool,
+    create_namespace: bool,
+) -> Result<()> {
+    status!(
+        "{} Replicating environment '{}' to {} context(s)",
+        "->".blue().bold(),
+        environment.cyan(),
+        contexts.len()
+    );
+
+    let prefetched = sync::prefetch(config, environment, false, DEFAULT_CONCURRENCY).await?;
+    status!(
+        "  Fetched {} secret(s)",
+        prefetched.secrets().len().to_string().green()
+    );
+
+    let mut failed: Vec<&str> = Vec::new();
+    for context in contexts {
+        status!("\n{} Context '{}'", "->".blue().bold(), context.cyan());
+
+        let options = SyncOptions {
+            namespace: namespace.clone(),
+            context: Some(context.clone()),
+            dry_run,
+            create_namespace,
+            concurrency: DEFAULT_CONCURRENCY,
+            ..Default::default()
+        };
+        if let Err(e) = sync::execute_prefetched(config, environment, options, &prefetched).await {
+            eprintln!("  {} Context '{}' failed: {:#}", "x".red(), context, e);
+            failed.push(context);
+        }
+    }
+
+    let succeeded = contexts.len() - failed.len();
+    if failed.is_empty() {
+        status!(
+            "\n{} Replicated to {} context(s)",
+            "OK".green().bold(),
+            succeeded
+        );
+        return Ok(());
+    }
+
+    eprintln!(
+        "\n{} Replicated to {} context(s), {} failed: {}",
+        "!".red().bold(),
+        succeeded.to_string().green(),
+        failed.len().to_string().red(),
+        failed.join(", ")
+    );
+    anyhow::bail!("{} of {} context(s) failed", failed.len(), contexts.len())
+}
@@ -0,0 +1,103 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+use crate::cache::Cache;
+use crate::config::Config;
+use crate::gcp::client::SecretVersionInfo;
+use crate::gcp::SecretManagerClient;
+use crate::output::status;
+
+pub async fn execute(config: &Config, name: &str, env: &str, to_version: &str) -> Result<()> {
+    config.validate_secret_id(env, name)?;
+
+    let gcp_client = SecretManagerClient::new(config.clone()).await?;
+
+    let versions = gcp_client.list_secret_versions(env, name).await?;
+    let current = latest_version(&versions).unwrap_or_else(|| "-".to_string());
+
+    if current == to_version {
+        anyhow::bail!(
+            "Version {} is already the latest version of '{}'",
+            to_version,
+            name
+        );
+    }
+
+    // Restore by re-adding the old value as a new version rather than
+    // changing the state of existing versions
+    let value = gcp_client
+        .get_secret_version_bytes(env, name, to_version)
+        .await
+        .with_context(|| format!("Failed to read version {} of secret: {}", to_version, name))?;
+
+    let new_version = gcp_client.add_secret_version(env, name, &value).await?;
+
+    // Invalidate cache so subsequent reads are fresh
+    if let Ok(mut cache) = Cache::load(config) {
+        cache.delete(env, name);
+        let _ = cache.save();
+    }
+
+    status!(
+        "{} Secret '{}' in environment '{}' rolled back to version {}",
+        "OK".green().bold(),
+        name.cyan(),
+        env.cyan(),
+        to_version.cyan()
+    );
+    status!(
+        "  Previous version: {}  New version: {}",
+        current,
+        new_version.green()
+    );
+
+    Ok(())
+}
+
+/// The version "latest" resolves to: the most recently created one, whatever
+/// its state. Listing order isn't guaranteed, so compare the numeric ids.
+fn latest_version(versions: &[SecretVersionInfo]) -> Option<String> {
+    versions
+        .iter()
+        .max_by_key(|v| v.version.parse::<u64>().unwrap_or(0))
+        .map(|v| v.version.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(number: u32, state: &str) -> SecretVersionInfo {
+        SecretVersionInfo {
+            version: number.to_string(),
+            state: state.to_string(),
+            created_at: None,
+            created_timestamp: None,
+            destroyed_at: None,
+            destroyed_timestamp: None,
+        }
+    }
+
+    #[test]
+    fn latest_version_is_the_highest_number() {
+        let versions = vec![
+            version(9, "ENABLED"),
+            version(11, "ENABLED"),
+            version(10, "ENABLED"),
+        ];
+
+        assert_eq!(latest_version(&versions).as_deref(), Some("11"));
+    }
+
+    #[test]
+    fn latest_version_counts_disabled_versions() {
+        let versions = vec![version(2, "ENABLED"), version(3, "DISABLED")];
+
+        assert_eq!(latest_version(&versions).as_deref(), Some("3"));
+    }
+
+    #[test]
+    fn latest_version_of_no_versions_is_none() {
+        assert_eq!(latest_version(&[]), None);
+    }
+}
@@ -1,26 +1,121 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use colored::Colorize;
 use std::io::{self, Read, Write};
+use std::time::Duration;
 
 use crate::cache::Cache;
 use crate::config::Config;
+use crate::error::{error_kind, ErrorKind};
+use crate::gcp::annotations::parse_annotations;
+use crate::gcp::client::{validate_secret_size, SecretVersionInfo};
+use crate::gcp::labels::parse_labels;
 use crate::gcp::SecretManagerClient;
+use crate::output::status;
 
-pub async fn execute(
-    config: &Config,
-    name: &str,
-    env: &str,
-    value: Option<String>,
-    stdin: bool,
-) -> Result<()> {
-    let secret_value = if stdin {
-        let mut buffer = String::new();
-        io::stdin()
-            .read_to_string(&mut buffer)
-            .context("Failed to read from stdin")?;
-        buffer.trim_end().to_string()
+/// How often a key merge is redone when the secret changes underneath it
+const MAX_MERGE_ATTEMPTS: usize = 3;
+
+/// Where the value of a `set` comes from
+#[derive(Debug, Default)]
+pub struct SetOptions {
+    /// Secret value (prompted for if no other source is given)
+    pub value: Option<String>,
+    /// Read the value from stdin
+    pub stdin: bool,
+    /// Keep trailing whitespace of a stdin or prompted value
+    pub no_trim: bool,
+    /// Read the value from a file, stored as-is
+    pub from_file: Option<String>,
+    /// Read the value from this environment variable, stored as-is
+    pub from_env: Option<String>,
+    /// Accept an empty `from_env` variable
+    pub allow_empty: bool,
+    /// Labels to attach to the secret
+    pub labels: Vec<String>,
+    /// Free-form annotations to attach to the secret
+    pub annotations: Vec<String>,
+    /// `key=value` pairs merged into a JSON multi-value secret
+    pub set_keys: Vec<String>,
+    /// Keys removed from a JSON multi-value secret
+    pub unset_keys: Vec<String>,
+    /// Show what would be written without changing anything
+    pub dry_run: bool,
+    /// Let GCP delete the secret after this long (e.g. `24h`, `30d`)
+    pub expire_in: Option<String>,
+    /// Destroy the oldest enabled versions beyond this many (overrides config)
+    pub max_versions: Option<usize>,
+    /// Only create the secret; leave an existing one unchanged
+    pub if_not_exists: bool,
+    /// With `if_not_exists`, fail instead of succeeding when it exists
+    pub fail_if_exists: bool,
+}
+
+pub async fn execute(config: &Config, name: &str, env: &str, options: SetOptions) -> Result<()> {
+    let SetOptions {
+        value,
+        stdin,
+        no_trim,
+        from_file,
+        from_env,
+        allow_empty,
+        labels,
+        annotations,
+        set_keys,
+        unset_keys,
+        dry_run,
+        expire_in,
+        max_versions,
+        if_not_exists,
+        fail_if_exists,
+    } = options;
+
+    let max_versions = max_versions.or(config.max_versions);
+    if max_versions == Some(0) {
+        anyhow::bail!("--max-versions must be at least 1");
+    }
+
+    config.validate_secret_id(env, name)?;
+
+    let sources = [
+        value.is_some(),
+        stdin,
+        from_file.is_some(),
+        from_env.is_some(),
+    ];
+    if sources.iter().filter(|s| **s).count() > 1 {
+        anyhow::bail!("Only one of --value, --stdin, --from-file and --from-env may be given");
+    }
+
+    let labels = parse_labels(&labels)?;
+    let annotations = parse_annotations(&annotations)?;
+
+    let expire_time = expire_in
+        .as_deref()
+        .map(|text| -> Result<DateTime<Utc>> {
+            let duration = parse_duration(text)?;
+            Ok(Utc::now() + chrono::Duration::from_std(duration)?)
+        })
+        .transpose()?;
+
+    let gcp_client = SecretManagerClient::new(config.clone()).await?;
+
+    let secret_value: Vec<u8> = if !set_keys.is_empty() || !unset_keys.is_empty() {
+        if sources.iter().any(|s| *s) {
+            anyhow::bail!(
+                "--set-key / --unset-key cannot be combined with --value, --stdin, --from-file or --from-env"
+            );
+        }
+        merge_keys(&gcp_client, env, name, &set_keys, &unset_keys).await?
+    } else if let Some(path) = from_file.as_deref() {
+        // File contents are stored as raw bytes, without trimming
+        std::fs::read(path).with_context(|| format!("Failed to read file: {}", path))?
+    } else if let Some(var) = from_env.as_deref() {
+        read_env_value(var, allow_empty)?
+    } else if stdin {
+        read_stdin_value(io::stdin(), !no_trim)?
     } else if let Some(v) = value {
-        v
+        v.into_bytes()
     } else {
         print!("Enter secret value: ");
         io::stdout().flush().context("Failed to flush stdout")?;
@@ -28,24 +123,375 @@ pub async fn execute(
         io::stdin()
             .read_line(&mut buffer)
             .context("Failed to read input")?;
-        buffer.trim_end().to_string()
+        if no_trim {
+            // Only the Enter that ended the prompt isn't part of the value
+            let line = buffer.strip_suffix('\n').unwrap_or(&buffer);
+            line.strip_suffix('\r').unwrap_or(line).as_bytes().to_vec()
+        } else {
+            buffer.trim_end().as_bytes().to_vec()
+        }
     };
 
-    let gcp_client = SecretManagerClient::new(config.clone()).await?;
-    gcp_client.set_secret(env, name, &secret_value).await?;
+    validate_secret_size(name, &secret_value)?;
 
-    // Update cache
-    if let Ok(mut cache) = Cache::load() {
-        cache.set(env, name, secret_value);
+    if dry_run {
+        let exists = gcp_client.secret_exists(env, name).await?;
+        if exists && if_not_exists {
+            return already_exists(config, env, name, fail_if_exists);
+        }
+        let action = if exists {
+            "add a new version to"
+        } else {
+            "create"
+        };
+        status!(
+            "{} Would {} {} ({} bytes)",
+            "->".blue().bold(),
+            action,
+            config.build_resource_name(env, name).cyan(),
+            secret_value.len()
+        );
+        if let Some(time) = expire_time {
+            status!("  Would expire at {}", format_expiry(time));
+        }
+        if let Some(max) = max_versions {
+            // The new version will take one of the kept slots
+            for version in excess_versions(&gcp_client, env, name, max - 1).await? {
+                status!("  Would destroy version {}", version);
+            }
+        }
+        status!("{}", "  (dry-run mode - no changes will be made)".yellow());
+        return Ok(());
+    }
+
+    if if_not_exists {
+        let created = gcp_client
+            .create_secret_bytes(env, name, &secret_value, &labels, &annotations, expire_time)
+            .await?;
+        if !created {
+            return already_exists(config, env, name, fail_if_exists);
+        }
+    } else {
+        gcp_client
+            .set_secret_bytes(env, name, &secret_value, &labels, &annotations, expire_time)
+            .await?;
+    }
+
+    // Update cache; non-UTF-8 values can't be cached, so drop any stale entry
+    if let Ok(mut cache) = Cache::load(config) {
+        match String::from_utf8(secret_value) {
+            Ok(text) => cache.set(env, name, text),
+            Err(_) => cache.delete(env, name),
+        }
+        cache.invalidate_list(env);
         let _ = cache.save();
     }
 
-    println!(
+    status!(
         "{} Secret '{}' set for environment '{}'",
         "OK".green().bold(),
         name.cyan(),
         env.cyan()
     );
+    if let Some(time) = expire_time {
+        status!("  Expires at {}", format_expiry(time).yellow());
+    }
+
+    if let Some(max) = max_versions {
+        for version in excess_versions(&gcp_client, env, name, max).await? {
+            gcp_client
+                .destroy_secret_version(env, name, &version)
+                .await?;
+            status!(
+                "  {} Destroyed version {} (keeping the newest {})",
+                "-".red(),
+                version,
+                max
+            );
+        }
+    }
+
+    Ok(())
+}
 
+/// Report that a create-only `set` left an existing secret alone, failing
+/// when `fail` is set
+fn already_exists(config: &Config, env: &str, name: &str, fail: bool) -> Result<()> {
+    if fail {
+        anyhow::bail!("Secret '{}' already exists in environment '{}'", name, env);
+    }
+
+    status!(
+        "{} {} already exists, left unchanged",
+        "!".yellow().bold(),
+        config.build_resource_name(env, name).cyan()
+    );
     Ok(())
 }
+
+/// Read a secret value piped to stdin. With `trim`, trailing whitespace
+/// (usually the final newline of `echo`) is dropped; otherwise the bytes are
+/// kept exactly, so PEM files and other binary input round-trip.
+fn read_stdin_value(mut reader: impl Read, trim: bool) -> Result<Vec<u8>> {
+    if !trim {
+        let mut buffer = Vec::new();
+        reader
+            .read_to_end(&mut buffer)
+            .context("Failed to read from stdin")?;
+        return Ok(buffer);
+    }
+
+    let mut buffer = String::new();
+    reader
+        .read_to_string(&mut buffer)
+        .context("Failed to read from stdin")?;
+    Ok(buffer.trim_end().as_bytes().to_vec())
+}
+
+/// Read a secret value from an environment variable, as-is. An unset
+/// variable is an error, and so is an empty one unless `allow_empty`.
+fn read_env_value(var: &str, allow_empty: bool) -> Result<Vec<u8>> {
+    let value = match std::env::var(var) {
+        Ok(value) => value,
+        Err(std::env::VarError::NotPresent) => {
+            anyhow::bail!("Environment variable '{}' is not set", var)
+        }
+        Err(std::env::VarError::NotUnicode(_)) => {
+            anyhow::bail!("Environment variable '{}' is not valid UTF-8", var)
+        }
+    };
+
+    if value.is_empty() && !allow_empty {
+        anyhow::bail!(
+            "Environment variable '{}' is empty (pass --allow-empty to store an empty value)",
+            var
+        );
+    }
+
+    Ok(value.into_bytes())
+}
+
+/// Enabled versions of a secret beyond the newest `keep` ones, oldest last
+async fn excess_versions(
+    gcp_client: &SecretManagerClient,
+    env: &str,
+    name: &str,
+    keep: usize,
+) -> Result<Vec<String>> {
+    match gcp_client.list_secret_versions(env, name).await {
+        Ok(versions) => Ok(versions_beyond(versions, keep)),
+        Err(e) if error_kind(&e) == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// The enabled versions beyond the newest `keep` ones, oldest last
+fn versions_beyond(versions: Vec<SecretVersionInfo>, keep: usize) -> Vec<String> {
+    let mut enabled: Vec<String> = versions
+        .into_iter()
+        .filter(|v| v.state == "ENABLED")
+        .map(|v| v.version)
+        .collect();
+
+    // Newest first, so the version just added is always kept
+    enabled.sort_by_key(|v| std::cmp::Reverse(v.parse::<u64>().unwrap_or(0)));
+
+    enabled.into_iter().skip(keep).collect()
+}
+
+fn format_expiry(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+/// Parse a human duration such as `90s`, `24h`, `30d` or `1d12h`
+pub fn parse_duration(text: &str) -> Result<Duration> {
+    let invalid = || {
+        anyhow::anyhow!(
+            "Invalid duration '{}': expected a number followed by s, m, h, d or w (e.g. 24h, 30d)",
+            text
+        )
+    };
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in text.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit: u64 = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        let value: u64 = number.parse().map_err(|_| invalid())?;
+        total = value
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+
+    if !number.is_empty() || total == 0 {
+        return Err(invalid());
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+/// Build the new value of a JSON multi-value secret by merging `set_keys` into
+/// (and removing `unset_keys` from) its latest value.
+///
+/// The latest value is read again right before returning; if another writer
+/// changed it in the meantime the merge is redone on top of their value. This
+/// is best-effort only: Secret Manager has no compare-and-set for new
+/// versions, so a write landing between the final read and our new version is
+/// still overwritten.
+async fn merge_keys(
+    gcp_client: &SecretManagerClient,
+    env: &str,
+    name: &str,
+    set_keys: &[String],
+    unset_keys: &[String],
+) -> Result<Vec<u8>> {
+    let updates = set_keys
+        .iter()
+        .map(|arg| {
+            arg.split_once('=')
+                .filter(|(key, _)| !key.is_empty())
+                .ok_or_else(|| anyhow::anyhow!("Invalid --set-key '{}': expected key=value", arg))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut current = read_latest(gcp_client, env, name).await?;
+
+    for attempt in 1..=MAX_MERGE_ATTEMPTS {
+        let mut map = match current.as_deref() {
+            None => serde_json::Map::new(),
+            Some(text) => match serde_json::from_str::<serde_json::Value>(text) {
+                Ok(serde_json::Value::Object(map)) => map,
+                _ => anyhow::bail!(
+                    "Secret '{}' is not a JSON object; --set-key / --unset-key only work on JSON multi-value secrets",
+                    name
+                ),
+            },
+        };
+
+        for (key, value) in &updates {
+            map.insert(
+                key.to_string(),
+                serde_json::Value::String(value.to_string()),
+            );
+        }
+        for key in unset_keys {
+            map.remove(key);
+        }
+
+        let merged = serde_json::to_string_pretty(&map)?;
+
+        // Best-effort guard against clobbering a concurrent writer
+        let latest = read_latest(gcp_client, env, name).await?;
+        if latest == current {
+            return Ok(merged.into_bytes());
+        }
+
+        status!(
+            "{} Secret '{}' changed while merging keys (attempt {}), merging again",
+            "!".yellow().bold(),
+            name,
+            attempt
+        );
+        current = latest;
+    }
+
+    anyhow::bail!(
+        "Secret '{}' keeps changing while merging keys; try again later",
+        name
+    )
+}
+
+/// Latest value of a secret as text, or `None` if it doesn't exist yet
+async fn read_latest(
+    gcp_client: &SecretManagerClient,
+    env: &str,
+    name: &str,
+) -> Result<Option<String>> {
+    gcp_client.get_secret_opt(env, name).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(number: u32, state: &str) -> SecretVersionInfo {
+        SecretVersionInfo {
+            version: number.to_string(),
+            state: state.to_string(),
+            created_at: None,
+            created_timestamp: None,
+            destroyed_at: None,
+            destroyed_timestamp: None,
+        }
+    }
+
+    #[test]
+    fn versions_beyond_keeps_the_newest() {
+        // Listed out of order, as pages may come back
+        let versions = [3, 10, 1, 7, 2, 9]
+            .into_iter()
+            .map(|n| version(n, "ENABLED"))
+            .collect();
+
+        let excess = versions_beyond(versions, 3);
+
+        assert_eq!(excess, ["3", "2", "1"]);
+        assert!(!excess.contains(&"10".to_string()));
+    }
+
+    #[test]
+    fn versions_beyond_ignores_disabled_and_destroyed_versions() {
+        let versions = vec![
+            version(5, "ENABLED"),
+            version(4, "DISABLED"),
+            version(3, "ENABLED"),
+            version(2, "DESTROYED"),
+            version(1, "ENABLED"),
+        ];
+
+        assert_eq!(versions_beyond(versions, 2), ["1"]);
+    }
+
+    #[test]
+    fn versions_beyond_is_empty_within_limit() {
+        let versions = vec![version(2, "ENABLED"), version(1, "ENABLED")];
+
+        assert!(versions_beyond(versions, 2).is_empty());
+    }
+
+    #[test]
+    fn read_stdin_value_keeps_trailing_newline_with_no_trim() {
+        let input = b"-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\n";
+
+        let value = read_stdin_value(&input[..], false).unwrap();
+
+        assert_eq!(value, input);
+    }
+
+    #[test]
+    fn read_stdin_value_trims_trailing_whitespace_by_default() {
+        let value = read_stdin_value(&b"hunter2\n"[..], true).unwrap();
+
+        assert_eq!(value, b"hunter2");
+    }
+
+    #[test]
+    fn read_stdin_value_keeps_binary_input_with_no_trim() {
+        let input = [0xff, 0x00, b'\n'];
+
+        assert_eq!(read_stdin_value(&input[..], false).unwrap(), input);
+    }
+}
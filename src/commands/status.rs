@@ -0,0 +1,211 @@
+use anyhow::Result;
+use colored::Colorize;
+use serde_json::json;
+
+use crate::config::Config;
+use crate::gcp::SecretManagerClient;
+use crate::k8s::client::KubeTimeouts;
+use crate::k8s::KubeClient;
+
+/// Result of one connectivity or diagnostic check, shared with `doctor`
+pub(super) struct Check {
+    pub name: &'static str,
+    pub outcome: Outcome,
+}
+
+pub(super) enum Outcome {
+    Ok(String),
+    /// Not run (e.g. an earlier check it depends on failed), with the reason
+    Skipped(String),
+    Failed(Problem),
+}
+
+/// What a failing check found and, if known, how to fix it
+pub(super) struct Problem {
+    pub message: String,
+    pub fix: Option<String>,
+    /// Whether ksecret can't work until this is fixed (as opposed to only
+    /// some commands being affected)
+    pub critical: bool,
+}
+
+impl Check {
+    pub fn ok(name: &'static str, detail: String) -> Self {
+        Check {
+            name,
+            outcome: Outcome::Ok(detail),
+        }
+    }
+
+    pub fn skipped(name: &'static str, reason: &str) -> Self {
+        Check {
+            name,
+            outcome: Outcome::Skipped(reason.to_string()),
+        }
+    }
+
+    pub fn failed(name: &'static str, message: String, fix: String, critical: bool) -> Self {
+        Check {
+            name,
+            outcome: Outcome::Failed(Problem {
+                message,
+                fix: Some(fix),
+                critical,
+            }),
+        }
+    }
+
+    /// A critical check whose error is reported without a fix
+    fn from_result(name: &'static str, result: Result<String>) -> Self {
+        match result {
+            Ok(detail) => Check::ok(name, detail),
+            Err(e) => Check {
+                name,
+                outcome: Outcome::Failed(Problem {
+                    message: format!("{:#}", e),
+                    fix: None,
+                    critical: true,
+                }),
+            },
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        matches!(self.outcome, Outcome::Ok(_))
+    }
+
+    /// Print the check as an aligned row, followed by its fix if it failed
+    pub fn print(&self) {
+        match &self.outcome {
+            Outcome::Ok(detail) => {
+                println!("  {:<12} {} {}", self.name, "OK".green().bold(), detail)
+            }
+            Outcome::Skipped(reason) => println!(
+                "  {:<12} {} {}",
+                self.name,
+                "SKIPPED".dimmed(),
+                reason.dimmed()
+            ),
+            Outcome::Failed(p) => {
+                let label = if p.critical {
+                    "FAILED".red().bold()
+                } else {
+                    "WARN".yellow().bold()
+                };
+                println!(
+                    "  {:<12} {} {}",
+                    self.name,
+                    label,
+                    p.message.replace('\n', "\n               ")
+                );
+                if let Some(fix) = &p.fix {
+                    println!(
+                        "  {:<12} {} {}",
+                        "",
+                        "fix:".cyan(),
+                        fix.replace('\n', "\n                    ")
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub async fn execute(
+    config: &Config,
+    namespace: &str,
+    context: Option<&str>,
+    output: &str,
+) -> Result<()> {
+    let config_path = Config::config_path()?;
+
+    let checks = vec![
+        Check::from_result("gcp", check_gcp(config).await),
+        Check::from_result(
+            "kubernetes",
+            check_kubernetes(config, namespace, context).await,
+        ),
+    ];
+
+    match output {
+        "json" => {
+            let checks: Vec<_> = checks
+                .iter()
+                .map(|c| match &c.outcome {
+                    Outcome::Ok(detail) => json!({ "name": c.name, "ok": true, "detail": detail }),
+                    Outcome::Skipped(reason) => {
+                        json!({ "name": c.name, "ok": false, "error": reason })
+                    }
+                    Outcome::Failed(p) => {
+                        json!({ "name": c.name, "ok": false, "error": p.message })
+                    }
+                })
+                .collect();
+            let output = json!({
+                "config_path": config_path,
+                "gcp_project": config.gcp_project_id,
+                "secret_prefix": config.secret_prefix,
+                "checks": checks
+            });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+        _ => {
+            println!("{} ksecret status\n", "->".blue().bold());
+            println!("  {:<14} {}", "Config:".bold(), config_path.display());
+            println!(
+                "  {:<14} {}",
+                "GCP project:".bold(),
+                config.gcp_project_id.cyan()
+            );
+            println!("  {:<14} {}", "Prefix:".bold(), config.secret_prefix.cyan());
+            println!();
+
+            for check in &checks {
+                check.print();
+            }
+        }
+    }
+
+    let failed = checks.iter().filter(|c| !c.is_ok()).count();
+    if failed > 0 {
+        anyhow::bail!("{} check(s) failed", failed);
+    }
+
+    Ok(())
+}
+
+/// Confirm GCP auth and permissions with a secret listing
+async fn check_gcp(config: &Config) -> Result<String> {
+    let gcp_client = SecretManagerClient::new(config.clone()).await?;
+    let secrets = gcp_client.list_all_secrets().await?;
+
+    Ok(format!(
+        "authenticated, {} secret(s) under prefix '{}'",
+        secrets.len(),
+        config.secret_prefix
+    ))
+}
+
+/// Confirm the Kubernetes context resolves and the namespace is reachable
+async fn check_kubernetes(
+    config: &Config,
+    namespace: &str,
+    context: Option<&str>,
+) -> Result<String> {
+    let context_name = KubeClient::context_name(context, config.in_cluster)?;
+    let timeouts = KubeTimeouts::from_config(config);
+    let k8s_client = KubeClient::new(context, config.in_cluster, timeouts).await?;
+
+    if !k8s_client.namespace_exists(namespace).await? {
+        anyhow::bail!(
+            "context '{}' is reachable but namespace '{}' does not exist",
+            context_name,
+            namespace
+        );
+    }
+
+    Ok(format!(
+        "context '{}', namespace '{}' reachable",
+        context_name, namespace
+    ))
+}
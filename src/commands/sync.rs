@@ -1,21 +1,400 @@
 use anyhow::{Context, Result};
 use colored::Colorize;
-use std::collections::BTreeMap;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 
+use crate::cache::list_secrets_cached;
 use crate::config::Config;
+use crate::content::{self, ContentType};
+use crate::gcp::client::SecretInfo;
 use crate::gcp::SecretManagerClient;
-use crate::k8s::KubeClient;
+use crate::k8s::client::{ApplyStrategy, DEFAULT_FIELD_MANAGER};
+use crate::k8s::{KubeClient, KubeTarget};
+use crate::metrics::{write_sync_metrics, SyncMetrics};
+use crate::output::{print_document, status, status_inline};
 
-pub async fn execute(
+/// GCP label overriding the Kubernetes secret type of a single secret
+const SECRET_TYPE_LABEL: &str = "ksecret-type";
+/// GCP label naming the content type of a single secret, or `none` to skip
+/// detection
+const CONTENT_TYPE_LABEL: &str = "ksecret-content";
+/// GCP label overriding the target namespace of a single secret
+pub const NAMESPACE_LABEL: &str = "ksecret-namespace";
+/// Annotation recording the GCP secret version a Kubernetes secret came from
+const SOURCE_VERSION_ANNOTATION: &str = "ksecret.io/source-version";
+/// Annotation recording when a Kubernetes secret was last synced (RFC 3339)
+const SYNCED_AT_ANNOTATION: &str = "ksecret.io/synced-at";
+/// Annotation listing the keys `--merge` wrote, comma-separated
+const MANAGED_KEYS_ANNOTATION: &str = "ksecret.io/managed-keys";
+/// Label recording the environment a Kubernetes secret was synced from
+const ENVIRONMENT_LABEL: &str = "ksecret.io/environment";
+/// Label recording the secret prefix a Kubernetes secret was synced from
+const PREFIX_LABEL: &str = "ksecret.io/prefix";
+
+/// Secrets fetched and applied at once unless `--concurrency` says otherwise
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Options controlling a sync run
+#[derive(Debug, Default)]
+pub struct SyncOptions {
+    /// Target Kubernetes namespace (defaults to environment name)
+    pub namespace: Option<String>,
+    /// Kubernetes context to use (defaults to current context)
+    pub context: Option<String>,
+    /// Perform a dry run without making changes
+    pub dry_run: bool,
+    /// Only sync these secrets
+    pub only: Vec<String>,
+    /// Sync all secrets except these
+    pub except: Vec<String>,
+    /// Create the target namespace if it does not exist
+    pub create_namespace: bool,
+    /// Delete managed Kubernetes secrets that no longer exist in GCP
+    pub prune: bool,
+    /// Prune without asking for confirmation
+    pub yes: bool,
+    /// Kubernetes secret type for synced secrets (defaults to Opaque)
+    pub secret_type: Option<String>,
+    /// Maximum number of secrets synced at the same time
+    pub concurrency: usize,
+    /// Keep syncing the remaining secrets after one fails
+    pub continue_on_error: bool,
+    /// Extra `key=value` labels for the Kubernetes secrets
+    pub labels: Vec<String>,
+    /// Extra `key=value` annotations for the Kubernetes secrets
+    pub annotations: Vec<String>,
+    /// Keep syncing on this interval (in seconds) until interrupted
+    pub watch: Option<u64>,
+    /// Server-side apply field manager (defaults to "ksecret")
+    pub field_manager: Option<String>,
+    /// Update secrets in place or delete and re-create them
+    pub apply_strategy: ApplyStrategy,
+    /// Mark the Kubernetes secrets immutable, re-creating them on change
+    pub immutable: bool,
+    /// Flatten nested multi-value secrets, joining key parts with this
+    /// separator
+    pub flatten: Option<String>,
+    /// Store detected single-file content (PEM, kubeconfig, ...) under its
+    /// conventional key instead of "value"
+    pub smart_keys: bool,
+    /// Keep keys of existing Kubernetes secrets that ksecret didn't write
+    pub merge: bool,
+    /// Read each secret back after applying it and compare its data
+    pub verify: bool,
+    /// Fall back to the newest enabled version when `latest` is disabled
+    pub resolve_enabled: bool,
+    /// Write every secret's keys into this one Kubernetes secret
+    pub combine: Option<String>,
+    /// With `combine`, prefix each key with the name of its source secret
+    pub prefix_keys: bool,
+    /// Without `namespace`, use the kube context's namespace (if it sets
+    /// one) instead of the environment name
+    pub namespace_from_context: bool,
+    /// Output format: "text" progress lines or a final "json" summary
+    pub output: String,
+    /// Write Prometheus textfile metrics about the run to this path
+    pub metrics_file: Option<PathBuf>,
+    /// List secrets from GCP instead of using a cached listing
+    pub refresh: bool,
+}
+
+/// What happened to one secret during a sync, for `--output json`
+#[derive(Debug, Serialize)]
+struct SecretOutcome<'a> {
+    name: &'a str,
+    namespace: &'a str,
+    /// "synced", "skipped" or "failed"
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// An environment's secrets and their values, read from GCP once so they can
+/// be synced to several clusters
+pub struct PrefetchedSecrets {
+    secrets: Vec<SecretInfo>,
+    /// Source version and value of each secret, by name
+    values: HashMap<String, (String, Vec<u8>)>,
+}
+
+impl PrefetchedSecrets {
+    /// The environment's secrets, as listed from GCP
+    pub fn secrets(&self) -> &[SecretInfo] {
+        &self.secrets
+    }
+}
+
+/// Clients and target shared by every secret of a sync run
+struct SyncContext<'a> {
+    config: &'a Config,
+    gcp_client: &'a SecretManagerClient,
+    k8s_client: &'a KubeClient,
+    environment: &'a str,
+    /// Namespace for secrets without a `ksecret-namespace` label
+    namespace: &'a str,
+    template: &'a SecretTemplate,
+    create_namespace: bool,
+    /// Read back every applied secret and check its data
+    verify: bool,
+    /// Fall back to the newest enabled version when `latest` is disabled
+    resolve_enabled: bool,
+    /// Values already fetched from GCP, used instead of fetching again
+    prefetched: Option<&'a PrefetchedSecrets>,
+    /// Progress of applying secrets; hidden until [`start_progress`]
+    progress: Option<ProgressBar>,
+}
+
+impl SyncContext<'_> {
+    /// Namespace a secret is synced to; its `ksecret-namespace` label
+    /// overrides the default
+    fn target_namespace<'s>(&'s self, secret_info: &'s SecretInfo) -> &'s str {
+        secret_info
+            .labels
+            .get(NAMESPACE_LABEL)
+            .map(String::as_str)
+            .unwrap_or(self.namespace)
+    }
+}
+
+/// Target of a `--combine` sync
+struct CombinedSecret<'a> {
+    /// Kubernetes secret all keys are written to
+    name: &'a str,
+    /// Prefix keys with their source secret's name to avoid collisions
+    prefix_keys: bool,
+}
+
+/// How secrets are written to Kubernetes
+struct SecretTemplate {
+    /// Type used unless a secret's `ksecret-type` label overrides it
+    default_type: String,
+    labels: BTreeMap<String, String>,
+    annotations: BTreeMap<String, String>,
+    /// Separator for flattening nested values, `None` to stringify them
+    flatten: Option<String>,
+    /// Detect single-file content and store it under its conventional key
+    smart_keys: bool,
+    /// Configured keys for detected content, by content type name
+    content_keys: BTreeMap<String, String>,
+    /// Overlay our keys on the existing secret instead of replacing its data
+    merge: bool,
+}
+
+impl SecretTemplate {
+    /// Kubernetes secret data for a GCP secret value
+    fn data(&self, value: Vec<u8>) -> BTreeMap<String, Vec<u8>> {
+        match self.flatten.as_deref() {
+            Some(separator) => flattened_secret_data(value, separator),
+            None => secret_data(value),
+        }
+    }
+
+    /// Like [`data`](Self::data), but a value holding single-file content
+    /// (detected with `smart_keys`, or named by the secret's
+    /// `ksecret-content` label) is stored whole under the key for its type
+    fn secret_data(
+        &self,
+        secret_info: &SecretInfo,
+        value: Vec<u8>,
+    ) -> Result<BTreeMap<String, Vec<u8>>> {
+        let content = match secret_info.labels.get(CONTENT_TYPE_LABEL) {
+            Some(label) if label == "none" => None,
+            Some(label) => Some(ContentType::from_name(label).with_context(|| {
+                format!(
+                    "Unknown {} label '{}' (use one of: {}, none)",
+                    CONTENT_TYPE_LABEL,
+                    label,
+                    ContentType::names()
+                )
+            })?),
+            None if self.smart_keys => content::detect(&value),
+            None => None,
+        };
+
+        Ok(match content {
+            Some(content) => {
+                let key = self
+                    .content_keys
+                    .get(content.name())
+                    .cloned()
+                    .unwrap_or_else(|| content.default_key().to_string());
+                BTreeMap::from([(key, value)])
+            }
+            None => self.data(value),
+        })
+    }
+}
+
+pub async fn execute(config: &Config, environment: &str, mut options: SyncOptions) -> Result<()> {
+    let mut metrics = SyncMetrics::default();
+    let Some(metrics_file) = options.metrics_file.take() else {
+        return run(config, environment, options, &mut metrics, None).await;
+    };
+
+    let result = run(config, environment, options, &mut metrics, None).await;
+    write_sync_metrics(&metrics_file, environment, &metrics, result.is_ok())?;
+    result
+}
+
+/// Sync secrets that were already fetched with [`prefetch`] instead of
+/// reading them from GCP again
+pub async fn execute_prefetched(
     config: &Config,
     environment: &str,
-    namespace: Option<String>,
-    context: Option<String>,
-    dry_run: bool,
+    options: SyncOptions,
+    prefetched: &PrefetchedSecrets,
 ) -> Result<()> {
-    let namespace = namespace.unwrap_or_else(|| environment.to_string());
+    let mut metrics = SyncMetrics::default();
+    run(config, environment, options, &mut metrics, Some(prefetched)).await
+}
+
+/// List an environment's secrets and fetch all of their values, honoring
+/// pinned versions
+pub async fn prefetch(
+    config: &Config,
+    environment: &str,
+    resolve_enabled: bool,
+    concurrency: usize,
+) -> Result<PrefetchedSecrets> {
+    config.validate_environment(environment)?;
+
+    let gcp_client = SecretManagerClient::new(config.clone())
+        .await
+        .context("Failed to initialize GCP client")?;
+
+    let secrets = gcp_client.list_secrets(environment).await?;
+    let values = stream::iter(&secrets)
+        .map(|secret_info| {
+            let gcp_client = &gcp_client;
+            async move {
+                let name = &secret_info.name;
+                let fetched =
+                    fetch_from_gcp(config, gcp_client, environment, name, resolve_enabled, None)
+                        .await?;
+                Ok::<_, anyhow::Error>((name.clone(), fetched))
+            }
+        })
+        .buffered(concurrency.max(1))
+        .try_collect()
+        .await?;
+
+    Ok(PrefetchedSecrets { secrets, values })
+}
 
-    println!(
+/// Run a sync, recording secret and failure counts in `metrics`. With
+/// `prefetched`, its secrets are synced instead of listing them from GCP.
+async fn run(
+    config: &Config,
+    environment: &str,
+    options: SyncOptions,
+    metrics: &mut SyncMetrics,
+    prefetched: Option<&PrefetchedSecrets>,
+) -> Result<()> {
+    let SyncOptions {
+        namespace,
+        context,
+        dry_run,
+        only,
+        except,
+        create_namespace,
+        prune,
+        yes,
+        secret_type,
+        concurrency,
+        continue_on_error,
+        labels,
+        annotations,
+        watch,
+        field_manager,
+        apply_strategy,
+        immutable,
+        flatten,
+        smart_keys,
+        merge,
+        verify,
+        resolve_enabled,
+        combine,
+        prefix_keys,
+        namespace_from_context,
+        output,
+        metrics_file: _,
+        refresh,
+    } = options;
+
+    // The JSON summary is the only thing written to stdout
+    let json_output = output == "json";
+    if json_output {
+        crate::output::set_quiet(true);
+        if prune && !yes {
+            anyhow::bail!("--prune with --output json requires --yes");
+        }
+    }
+
+    config.validate_environment(environment)?;
+
+    // Watch cycles run unattended, so there is nobody to confirm a prune
+    if prune && watch.is_some() && !yes {
+        anyhow::bail!("--prune with --watch requires --yes");
+    }
+
+    // CLI labels / annotations are merged over the ones from config
+    let mut extra_labels = config.k8s_labels.clone();
+    extra_labels.extend(parse_key_values(&labels, "label")?);
+    extra_labels.extend(source_labels(config, environment));
+    let mut extra_annotations = config.k8s_annotations.clone();
+    extra_annotations.extend(parse_key_values(&annotations, "annotation")?);
+
+    if let Some(unknown) = config
+        .content_type_keys
+        .keys()
+        .find(|name| ContentType::from_name(name).is_none())
+    {
+        anyhow::bail!(
+            "Unknown content type '{}' in content_type_keys (use one of: {})",
+            unknown,
+            ContentType::names()
+        );
+    }
+
+    let template = SecretTemplate {
+        default_type: resolve_secret_type(secret_type.as_deref().unwrap_or("Opaque")),
+        labels: extra_labels,
+        annotations: extra_annotations,
+        flatten,
+        smart_keys,
+        content_keys: config.content_type_keys.clone(),
+        merge,
+    };
+
+    if !only.is_empty() && !except.is_empty() {
+        anyhow::bail!("--only and --except cannot be used together");
+    }
+
+    let mut kube_target =
+        KubeTarget::with_context_namespace(environment, namespace, context, namespace_from_context);
+
+    // Initialize clients; the namespace may come from the kube context
+    let gcp_client = SecretManagerClient::new(config.clone())
+        .await
+        .context("Failed to initialize GCP client")?;
+
+    let k8s_client = kube_target
+        .connect(config)
+        .await?
+        .with_field_manager(field_manager.as_deref().unwrap_or(DEFAULT_FIELD_MANAGER))?
+        .with_apply_strategy(apply_strategy)
+        .with_immutable(immutable);
+    let namespace = kube_target.namespace.as_str();
+
+    status!(
         "{} Syncing secrets for environment '{}' to namespace '{}'",
         "->".blue().bold(),
         environment.cyan(),
@@ -23,110 +402,1100 @@ pub async fn execute(
     );
 
     if dry_run {
-        println!("{}", "  (dry-run mode - no changes will be made)".yellow());
+        status!("{}", "  (dry-run mode - no changes will be made)".yellow());
     }
 
-    // Initialize clients
-    let gcp_client = SecretManagerClient::new(config.clone())
-        .await
-        .context("Failed to initialize GCP client")?;
+    ensure_namespace(&k8s_client, namespace, create_namespace, dry_run).await?;
 
-    let k8s_client = KubeClient::new(context.as_deref())
-        .await
-        .context("Failed to initialize Kubernetes client")?;
+    let ctx = SyncContext {
+        config,
+        gcp_client: &gcp_client,
+        k8s_client: &k8s_client,
+        environment,
+        namespace,
+        template: &template,
+        create_namespace,
+        verify,
+        resolve_enabled,
+        prefetched,
+        // Only for an interactive terminal; logs get the line-per-secret output
+        progress: show_progress().then(ProgressBar::hidden),
+    };
 
-    // Check if namespace exists
-    if !k8s_client.namespace_exists(&namespace).await? {
-        anyhow::bail!("Namespace '{}' does not exist", namespace);
+    if let Some(interval) = watch {
+        return watch_secrets(&ctx, &only, &except, prune, concurrency, interval).await;
     }
 
-    // List secrets from GCP
-    let secrets = gcp_client.list_secrets(environment).await?;
+    // List secrets from GCP; pruning compares against the full, unfiltered set,
+    // so it never trusts a cached listing that may miss a new secret
+    let all_secrets = match prefetched {
+        Some(prefetched) => prefetched.secrets.clone(),
+        None => list_secrets_cached(config, &gcp_client, environment, refresh || prune).await?,
+    };
+    let known = known_by_namespace(&ctx, &all_secrets);
+    let secrets = filter_secrets(all_secrets, &only, &except)?;
 
     if secrets.is_empty() {
-        println!(
+        status!(
             "{} No secrets found for environment '{}'",
             "!".yellow().bold(),
             environment
         );
+        if prune {
+            let source = source_labels(config, environment);
+            prune_namespaces(&k8s_client, &known, &source, dry_run, yes).await?;
+        }
+        if json_output {
+            print_report(environment, namespace, dry_run, &[])?;
+        }
         return Ok(());
     }
 
-    println!("  Found {} secret(s) to sync", secrets.len().to_string().green());
+    status!("  Found {} secret(s) to sync", secrets.len().to_string().green());
+    metrics.secrets = secrets.len();
 
-    // Sync each secret
-    for secret_info in &secrets {
-        print!("  {} {}... ", "->".blue(), secret_info.name);
+    if let Some(combined_name) = combine.as_deref() {
+        let combined = CombinedSecret {
+            name: combined_name,
+            prefix_keys,
+        };
+        let result = sync_combined(&ctx, &secrets, &combined, concurrency, dry_run).await;
+        // Every secret ends up in the one Kubernetes secret, so they fail together
+        if result.is_err() {
+            metrics.failures = secrets.len();
+        }
+        return result;
+    }
 
-        if dry_run {
-            println!("{}", "skipped (dry-run)".yellow());
-            continue;
+    // Secrets routed elsewhere by their namespace label need their own check
+    let targets: BTreeSet<&str> = secrets.iter().map(|s| ctx.target_namespace(s)).collect();
+    for target in targets.iter().filter(|t| **t != namespace) {
+        ensure_namespace(&k8s_client, target, create_namespace, dry_run).await?;
+    }
+
+    let mut failed: Vec<(&str, anyhow::Error)> = Vec::new();
+    let mut skipped = 0;
+    let mut synced: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut outcomes: Vec<SecretOutcome> = Vec::new();
+
+    if dry_run {
+        for secret_info in &secrets {
+            status!(
+                "  {} {}... {}",
+                "->".blue(),
+                display_name(&ctx, secret_info),
+                "skipped (dry-run)".yellow()
+            );
+            outcomes.push(SecretOutcome {
+                name: &secret_info.name,
+                namespace: ctx.target_namespace(secret_info),
+                status: "skipped",
+                error: None,
+            });
+        }
+    } else {
+        if let Some(progress) = &ctx.progress {
+            start_progress(progress, secrets.len());
+        }
+        let results = sync_all(&ctx, &secrets, concurrency, !continue_on_error, None).await;
+        if let Some(progress) = &ctx.progress {
+            progress.finish_and_clear();
         }
 
-        // Get secret value from GCP
-        let value = gcp_client
-            .get_secret(environment, &secret_info.name)
-            .await
-            .with_context(|| format!("Failed to get secret: {}", secret_info.name))?;
+        for (secret_info, result) in secrets.iter().zip(results) {
+            let name = secret_info.name.as_str();
+            let target = ctx.target_namespace(secret_info);
+            let display = display_name(&ctx, secret_info);
+            let (status, error) = match result {
+                Some(Ok(_)) => {
+                    status!("  {} {}... {}", "->".blue(), display, "done".green());
+                    *synced.entry(target).or_default() += 1;
+                    ("synced", None)
+                }
+                Some(Err(e)) => {
+                    status!("  {} {}... {}", "->".blue(), display, "failed".red());
+                    let message = format!("{:#}", e);
+                    failed.push((name, e));
+                    ("failed", Some(message))
+                }
+                None => {
+                    status!("  {} {}... {}", "->".blue(), display, "skipped".yellow());
+                    skipped += 1;
+                    ("skipped", None)
+                }
+            };
+            outcomes.push(SecretOutcome {
+                name,
+                namespace: target,
+                status,
+                error,
+            });
+        }
 
-        // Determine if secret is multi-value (JSON/YAML) or single value
-        let mut data = BTreeMap::new();
-        let mut is_multi_value = false;
+        metrics.failures = failed.len();
 
-        // Try parsing as JSON Object first
-        if let Ok(serde_json::Value::Object(map)) = serde_json::from_str::<serde_json::Value>(&value) {
-            is_multi_value = true;
-            for (k, v) in map {
-                let v_str = match v {
-                    serde_json::Value::String(s) => s,
-                    _ => v.to_string(),
-                };
-                data.insert(k, v_str.into_bytes());
+        if !failed.is_empty() && !continue_on_error {
+            if json_output {
+                print_report(environment, namespace, dry_run, &outcomes)?;
             }
+            report_failures(&failed, secrets.len(), skipped);
+            eprintln!("  Use --continue-on-error to sync the remaining secrets anyway");
+            anyhow::bail!("{} secret(s) failed to sync", failed.len());
         }
-        // If not JSON object, try parsing as YAML Mapping
-        else if let Ok(serde_yaml::Value::Mapping(map)) = serde_yaml::from_str::<serde_yaml::Value>(&value) {
-            is_multi_value = true;
-            for (k, v) in map {
-                if let Some(k_str) = k.as_str() {
-                    let v_str = match v {
-                        serde_yaml::Value::String(s) => s,
-                        serde_yaml::Value::Bool(b) => b.to_string(),
-                        serde_yaml::Value::Number(n) => n.to_string(),
-                        _ => {
-                            // Serialize complex types back to string, trimming the newline usually added by to_string
-                            serde_yaml::to_string(&v)
-                                .unwrap_or_default()
-                                .trim()
-                                .to_string()
+    }
+
+    if prune {
+        let source = source_labels(config, environment);
+        prune_namespaces(&k8s_client, &known, &source, dry_run, yes).await?;
+    }
+
+    if json_output {
+        print_report(environment, namespace, dry_run, &outcomes)?;
+    }
+
+    if !failed.is_empty() {
+        report_failures(&failed, secrets.len(), skipped);
+        anyhow::bail!("{} secret(s) failed to sync", failed.len());
+    }
+
+    if targets.len() > 1 && !dry_run {
+        status!(
+            "\n{} Successfully synced {} secret(s) to {} namespaces:",
+            "OK".green().bold(),
+            secrets.len(),
+            targets.len()
+        );
+        for (target, count) in &synced {
+            status!("  {}: {} secret(s)", target.cyan(), count);
+        }
+    } else {
+        status!(
+            "\n{} Successfully synced {} secret(s) to namespace '{}'",
+            "OK".green().bold(),
+            secrets.len(),
+            namespace.cyan()
+        );
+    }
+
+    Ok(())
+}
+
+/// Make sure a namespace exists, creating it when `create` is set
+async fn ensure_namespace(
+    k8s_client: &KubeClient,
+    namespace: &str,
+    create: bool,
+    dry_run: bool,
+) -> Result<()> {
+    if k8s_client.namespace_exists(namespace).await? {
+        return Ok(());
+    }
+
+    if !create {
+        anyhow::bail!(
+            "Namespace '{}' does not exist (use --create-namespace to create it)",
+            namespace
+        );
+    }
+
+    if dry_run {
+        status!("  Would create namespace '{}'", namespace.cyan());
+    } else {
+        k8s_client.create_namespace(namespace).await?;
+        status!("  Created namespace '{}'", namespace.cyan());
+    }
+
+    Ok(())
+}
+
+/// Secret name for progress output, with its namespace when it's routed away
+/// from the default one
+fn display_name(ctx: &SyncContext<'_>, secret_info: &SecretInfo) -> String {
+    let target = ctx.target_namespace(secret_info);
+    if target == ctx.namespace {
+        secret_info.name.clone()
+    } else {
+        format!("{}/{}", target, secret_info.name)
+    }
+}
+
+/// Group Kubernetes secret names by the namespace they are synced to. The
+/// default namespace is always present so it gets pruned even when nothing
+/// targets it.
+///
+/// A secret whose name doesn't render to a valid Kubernetes name is left out
+/// with a warning: it can't have been synced under that name, and it may be
+/// excluded by --only/--except. Syncing it fails on its own.
+fn known_by_namespace(
+    ctx: &SyncContext<'_>,
+    secrets: &[SecretInfo],
+) -> BTreeMap<String, Vec<String>> {
+    let mut known = BTreeMap::from([(ctx.namespace.to_string(), Vec::new())]);
+    for secret_info in secrets {
+        let k8s_name = match ctx.config.k8s_secret_name(&secret_info.name) {
+            Ok(k8s_name) => k8s_name,
+            Err(e) => {
+                eprintln!(
+                    "  {} Skipping '{}': {:#}",
+                    "!".yellow().bold(),
+                    secret_info.name,
+                    e
+                );
+                continue;
+            }
+        };
+        known
+            .entry(ctx.target_namespace(secret_info).to_string())
+            .or_insert_with(Vec::new)
+            .push(k8s_name);
+    }
+    known
+}
+
+/// Print the `--output json` summary of a sync
+fn print_report(
+    environment: &str,
+    namespace: &str,
+    dry_run: bool,
+    outcomes: &[SecretOutcome],
+) -> Result<()> {
+    let count = |status: &str| outcomes.iter().filter(|o| o.status == status).count();
+    let report = json!({
+        "environment": environment,
+        "namespace": namespace,
+        "dry_run": dry_run,
+        "secrets": outcomes,
+        "counts": {
+            "total": outcomes.len(),
+            "synced": count("synced"),
+            "skipped": count("skipped"),
+            "failed": count("failed")
+        }
+    });
+    print_document(&report, "json")
+}
+
+/// Print a summary of a sync that had failures, naming each failed secret
+fn report_failures(failed: &[(&str, anyhow::Error)], total: usize, skipped: usize) {
+    let synced = total - failed.len() - skipped;
+    eprintln!(
+        "\n{} Synced {} secret(s), {} failed, {} skipped:",
+        "!".red().bold(),
+        synced.to_string().green(),
+        failed.len().to_string().red(),
+        skipped
+    );
+    for (name, e) in failed {
+        eprintln!("  {} {}: {:#}", "x".red(), name, e);
+    }
+}
+
+/// Re-run the sync every `interval` seconds until interrupted, only applying
+/// secrets whose value changed since the previous cycle
+async fn watch_secrets(
+    ctx: &SyncContext<'_>,
+    only: &[String],
+    except: &[String],
+    prune: bool,
+    concurrency: usize,
+    interval: u64,
+) -> Result<()> {
+    status!(
+        "  Watching for changes every {}s (Ctrl+C to stop)",
+        interval.to_string().cyan()
+    );
+
+    let hashes = Mutex::new(HashMap::new());
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(interval.max(1)));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    loop {
+        tokio::select! {
+            _ = &mut ctrl_c => break,
+            _ = async {
+                ticker.tick().await;
+                let time = chrono::Local::now().format("%H:%M:%S");
+                match watch_cycle(ctx, only, except, prune, concurrency, &hashes).await {
+                    Ok(failed) => {
+                        for (name, e) in &failed {
+                            eprintln!("  {} {}: {:#}", "x".red(), name, e);
                         }
-                    };
-                    data.insert(k_str.to_string(), v_str.into_bytes());
+                    }
+                    Err(e) => eprintln!("  [{}] {} {:#}", time, "!".red().bold(), e),
+                }
+            } => {}
+        }
+    }
+
+    status!("\n{} Stopped watching", "OK".green().bold());
+    Ok(())
+}
+
+/// Run one watch cycle, printing a one-line summary and returning the
+/// secrets that failed
+async fn watch_cycle(
+    ctx: &SyncContext<'_>,
+    only: &[String],
+    except: &[String],
+    prune: bool,
+    concurrency: usize,
+    hashes: &Mutex<HashMap<String, Vec<u8>>>,
+) -> Result<Vec<(String, anyhow::Error)>> {
+    let all_secrets = ctx.gcp_client.list_secrets(ctx.environment).await?;
+    let known = known_by_namespace(ctx, &all_secrets);
+
+    // Forget secrets that disappeared so they're applied again if re-created
+    hashes
+        .lock()
+        .unwrap()
+        .retain(|name, _| all_secrets.iter().any(|s| &s.name == name));
+
+    let secrets = filter_secrets(all_secrets, only, except)?;
+
+    // A namespace label may have been added since the last cycle
+    let targets: BTreeSet<&str> = secrets.iter().map(|s| ctx.target_namespace(s)).collect();
+    for target in targets.iter().filter(|t| **t != ctx.namespace) {
+        ensure_namespace(ctx.k8s_client, target, ctx.create_namespace, false).await?;
+    }
+
+    let results = sync_all(ctx, &secrets, concurrency, false, Some(hashes)).await;
+
+    let mut changed = 0;
+    let mut failed = Vec::new();
+    for (secret_info, result) in secrets.iter().zip(results) {
+        match result {
+            Some(Ok(true)) => changed += 1,
+            Some(Err(e)) => failed.push((secret_info.name.clone(), e)),
+            _ => {}
+        }
+    }
+
+    if prune {
+        let source = source_labels(ctx.config, ctx.environment);
+        prune_namespaces(ctx.k8s_client, &known, &source, false, true).await?;
+    }
+
+    let unchanged = secrets.len() - changed - failed.len();
+    let summary = format!(
+        "  [{}] {} changed, {} unchanged",
+        chrono::Local::now().format("%H:%M:%S"),
+        changed,
+        unchanged
+    );
+    if failed.is_empty() {
+        status!("{}", summary);
+    } else {
+        status!("{}, {} failed", summary, failed.len().to_string().red());
+    }
+
+    Ok(failed)
+}
+
+/// Sync secrets concurrently, returning one result per secret in input order.
+///
+/// With `stop_on_error`, the first failure stops any secrets that haven't
+/// started yet (their result is `None`); in-flight ones still finish.
+async fn sync_all(
+    ctx: &SyncContext<'_>,
+    secrets: &[SecretInfo],
+    concurrency: usize,
+    stop_on_error: bool,
+    hashes: Option<&Mutex<HashMap<String, Vec<u8>>>>,
+) -> Vec<Option<Result<bool>>> {
+    let abort = AtomicBool::new(false);
+
+    // Fetch and apply concurrently, then restore the original order so the
+    // output stays deterministic
+    let mut results: Vec<(usize, Option<Result<bool>>)> = stream::iter(secrets.iter().enumerate())
+        .map(|(index, secret_info)| {
+            let abort = &abort;
+            async move {
+                if abort.load(Ordering::Relaxed) {
+                    return (index, None);
+                }
+                if let Some(progress) = &ctx.progress {
+                    progress.set_message(secret_info.name.clone());
+                }
+                let result = sync_secret(ctx, secret_info, hashes).await;
+                if result.is_err() && stop_on_error {
+                    abort.store(true, Ordering::Relaxed);
                 }
+                if let Some(progress) = &ctx.progress {
+                    progress.inc(1);
+                }
+                (index, Some(result))
+            }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+    results.sort_by_key(|(index, _)| *index);
+
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Fetch a single secret from GCP and apply it to the namespace.
+///
+/// When `hashes` is given, the secret is only applied if its value changed
+/// since the hash was recorded. Returns whether it was applied.
+async fn sync_secret(
+    ctx: &SyncContext<'_>,
+    secret_info: &SecretInfo,
+    hashes: Option<&Mutex<HashMap<String, Vec<u8>>>>,
+) -> Result<bool> {
+    let name = &secret_info.name;
+
+    let (source_version, value) = fetch_secret(ctx, name).await?;
+
+    // A per-secret label overrides the --secret-type default
+    let secret_type = secret_info
+        .labels
+        .get(SECRET_TYPE_LABEL)
+        .map(|t| resolve_secret_type(t))
+        .unwrap_or_else(|| ctx.template.default_type.clone());
+
+    let namespace = ctx.target_namespace(secret_info);
+    let k8s_name = ctx.config.k8s_secret_name(name)?;
+
+    // The namespace is part of the hash so a re-routed secret gets applied
+    let mut hasher = Sha256::new();
+    hasher.update(namespace.as_bytes());
+    hasher.update([0]);
+    hasher.update(secret_type.as_bytes());
+    hasher.update([0]);
+    hasher.update(&value);
+    let hash = hasher.finalize().to_vec();
+
+    if let Some(hashes) = hashes {
+        if hashes.lock().unwrap().get(name) == Some(&hash) {
+            return Ok(false);
+        }
+    }
+
+    let data = ctx
+        .template
+        .secret_data(secret_info, value)
+        .with_context(|| format!("Invalid secret: {}", name))?;
+    validate_secret_type(&secret_type, &data)
+        .with_context(|| format!("Invalid secret: {}", name))?;
+
+    // Record where the value came from; being part of our apply, the
+    // annotations are owned by our field manager
+    let mut annotations = ctx.template.annotations.clone();
+    annotations.insert(SOURCE_VERSION_ANNOTATION.to_string(), source_version);
+    annotations.insert(
+        SYNCED_AT_ANNOTATION.to_string(),
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+    );
+
+    // Server-side apply already keeps keys owned by other field managers, so
+    // they're only carried over when the secret is deleted and re-created
+    let data = if ctx.template.merge {
+        annotations.insert(
+            MANAGED_KEYS_ANNOTATION.to_string(),
+            data.keys().cloned().collect::<Vec<_>>().join(","),
+        );
+        match ctx.k8s_client.apply_strategy() {
+            ApplyStrategy::Patch => data,
+            ApplyStrategy::Recreate => {
+                merge_existing(ctx.k8s_client, namespace, &k8s_name, data).await?
             }
         }
+    } else {
+        data
+    };
 
-        // Fallback to single value if parsing failed or didn't yield a map
-        if !is_multi_value || data.is_empty() {
-            data.clear(); // Ensure empty if partial parse
-            data.insert("value".to_string(), value.into_bytes());
+    let expected = ctx.verify.then(|| data.clone());
+
+    // Apply to Kubernetes
+    ctx.k8s_client
+        .apply_secret(
+            namespace,
+            &k8s_name,
+            data,
+            &secret_type,
+            &ctx.template.labels,
+            &annotations,
+        )
+        .await
+        .with_context(|| format!("Failed to apply secret: {}", name))?;
+
+    if let Some(expected) = expected {
+        verify_secret(ctx.k8s_client, namespace, &k8s_name, &expected).await?;
+    }
+
+    if let Some(hashes) = hashes {
+        hashes.lock().unwrap().insert(name.clone(), hash);
+    }
+
+    Ok(true)
+}
+
+/// Overlay `data` on the keys of the existing Kubernetes secret, for
+/// `--merge`. Keys an earlier merge wrote (per the managed-keys annotation)
+/// that are no longer in `data` are dropped; keys written by anyone else are
+/// kept.
+async fn merge_existing(
+    k8s_client: &KubeClient,
+    namespace: &str,
+    name: &str,
+    data: BTreeMap<String, Vec<u8>>,
+) -> Result<BTreeMap<String, Vec<u8>>> {
+    let Some((mut merged, annotations)) = k8s_client
+        .get_secret_with_annotations(namespace, name)
+        .await?
+    else {
+        return Ok(data);
+    };
+
+    if let Some(previous) = annotations.get(MANAGED_KEYS_ANNOTATION) {
+        for key in previous.split(',') {
+            merged.remove(key);
         }
+    }
+    merged.extend(data);
 
-        // Apply to Kubernetes
-        k8s_client
-            .apply_secret(&namespace, &secret_info.name, data)
-            .await
-            .with_context(|| format!("Failed to apply secret: {}", secret_info.name))?;
+    Ok(merged)
+}
+
+/// Get a secret's value from GCP, honoring any pinned version. Returns the
+/// version that was read and its raw bytes, so binary secrets reach
+/// Kubernetes unchanged.
+async fn fetch_secret(ctx: &SyncContext<'_>, name: &str) -> Result<(String, Vec<u8>)> {
+    if let Some(fetched) = ctx.prefetched.and_then(|p| p.values.get(name)) {
+        return Ok(fetched.clone());
+    }
+
+    fetch_from_gcp(
+        ctx.config,
+        ctx.gcp_client,
+        ctx.environment,
+        name,
+        ctx.resolve_enabled,
+        ctx.progress.as_ref(),
+    )
+    .await
+}
+
+/// Read a secret's pinned (or latest) version from GCP, warning when
+/// `resolve_enabled` fell back to an older enabled version
+async fn fetch_from_gcp(
+    config: &Config,
+    gcp_client: &SecretManagerClient,
+    environment: &str,
+    name: &str,
+    resolve_enabled: bool,
+    progress: Option<&ProgressBar>,
+) -> Result<(String, Vec<u8>)> {
+    let version = config.pinned_version(environment, name);
+    let (source_version, value, fell_back) = gcp_client
+        .access_secret_version_resolving(environment, name, version, resolve_enabled)
+        .await
+        .with_context(|| format!("Failed to get secret: {}", name))?;
 
-        println!("{}", "done".green());
+    if fell_back {
+        let warning = format!(
+            "  {} Latest version of '{}' is not enabled; using version {}",
+            "!".yellow().bold(),
+            name,
+            source_version
+        );
+        // Printed above the bar rather than through it
+        match progress {
+            Some(progress) => progress.suspend(|| eprintln!("{}", warning)),
+            None => eprintln!("{}", warning),
+        }
     }
 
-    println!(
-        "\n{} Successfully synced {} secret(s) to namespace '{}'",
+    Ok((source_version, value))
+}
+
+/// Whether to draw a progress bar: only when both stdout and stderr (where
+/// the bar is drawn) are terminals and progress output isn't silenced
+fn show_progress() -> bool {
+    !crate::output::is_quiet() && io::stdout().is_terminal() && io::stderr().is_terminal()
+}
+
+/// Show `progress` on stderr, counting up to `total` secrets
+fn start_progress(progress: &ProgressBar, total: usize) {
+    if let Ok(style) = ProgressStyle::with_template("  {bar:30.cyan/blue} {pos}/{len} {wide_msg}") {
+        progress.set_style(style.progress_chars("=> "));
+    }
+    progress.set_length(total as u64);
+    progress.set_draw_target(ProgressDrawTarget::stderr());
+}
+
+/// Fetch every secret and apply all of their keys as a single Kubernetes
+/// secret in the default namespace.
+///
+/// Single-value secrets are stored under their secret name. A key provided by
+/// two secrets is an error unless keys are prefixed with their source name.
+async fn sync_combined(
+    ctx: &SyncContext<'_>,
+    secrets: &[SecretInfo],
+    combined: &CombinedSecret<'_>,
+    concurrency: usize,
+    dry_run: bool,
+) -> Result<()> {
+    status!(
+        "  {} Combining into secret '{}'",
+        "->".blue(),
+        combined.name.cyan()
+    );
+
+    let values: Vec<(&str, Vec<u8>)> = stream::iter(secrets)
+        .map(|secret_info| async move {
+            let (_, value) = fetch_secret(ctx, &secret_info.name).await?;
+            Ok::<_, anyhow::Error>((secret_info.name.as_str(), value))
+        })
+        .buffered(concurrency.max(1))
+        .try_collect()
+        .await?;
+
+    let mut data = BTreeMap::new();
+    let mut sources: HashMap<String, &str> = HashMap::new();
+    for (name, value) in values {
+        let is_multi_value = std::str::from_utf8(&value)
+            .ok()
+            .and_then(parse_multi_value)
+            .is_some();
+        let entries = if is_multi_value {
+            ctx.template.data(value)
+        } else {
+            BTreeMap::from([(name.to_string(), value)])
+        };
+
+        for (key, value) in entries {
+            let key = if combined.prefix_keys && is_multi_value {
+                format!("{}_{}", name, key)
+            } else {
+                key
+            };
+            if let Some(previous) = sources.insert(key.clone(), name) {
+                anyhow::bail!(
+                    "Key '{}' is provided by both '{}' and '{}' (use --prefix-keys to keep both)",
+                    key,
+                    previous,
+                    name
+                );
+            }
+            data.insert(key, value);
+        }
+    }
+
+    let secret_type = &ctx.template.default_type;
+    validate_secret_type(secret_type, &data)
+        .with_context(|| format!("Invalid secret: {}", combined.name))?;
+    let key_count = data.len();
+
+    if dry_run {
+        status!(
+            "\n{} Would apply {} key(s) from {} secret(s) to '{}' in namespace '{}'",
+            "->".blue().bold(),
+            key_count,
+            secrets.len(),
+            combined.name,
+            ctx.namespace.cyan()
+        );
+        return Ok(());
+    }
+
+    let mut annotations = ctx.template.annotations.clone();
+    annotations.insert(
+        SYNCED_AT_ANNOTATION.to_string(),
+        chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+    );
+
+    let expected = ctx.verify.then(|| data.clone());
+
+    ctx.k8s_client
+        .apply_secret(
+            ctx.namespace,
+            combined.name,
+            data,
+            secret_type,
+            &ctx.template.labels,
+            &annotations,
+        )
+        .await
+        .with_context(|| format!("Failed to apply secret: {}", combined.name))?;
+
+    if let Some(expected) = expected {
+        verify_secret(ctx.k8s_client, ctx.namespace, combined.name, &expected).await?;
+    }
+
+    status!(
+        "\n{} Applied {} key(s) from {} secret(s) to '{}' in namespace '{}'",
         "OK".green().bold(),
+        key_count,
         secrets.len(),
-        namespace.cyan()
+        combined.name,
+        ctx.namespace.cyan()
+    );
+
+    Ok(())
+}
+
+/// SHA-256 over a secret's data map, as hex
+fn data_hash(data: &BTreeMap<String, Vec<u8>>) -> String {
+    let mut hasher = Sha256::new();
+    for (key, value) in data {
+        // Length-prefix the values so key/value boundaries can't shift
+        hasher.update(key.as_bytes());
+        hasher.update([0]);
+        hasher.update((value.len() as u64).to_be_bytes());
+        hasher.update(value);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Read a secret back from Kubernetes and check that the keys that were
+/// applied hash to what was sent, catching truncation or mutation by admission
+/// webhooks. Keys owned by other field managers are ignored.
+async fn verify_secret(
+    k8s_client: &KubeClient,
+    namespace: &str,
+    name: &str,
+    applied: &BTreeMap<String, Vec<u8>>,
+) -> Result<()> {
+    let data: BTreeMap<String, Vec<u8>> = k8s_client
+        .get_secret(namespace, name)
+        .await?
+        .with_context(|| format!("Secret '{}' disappeared after applying it", name))?
+        .into_iter()
+        .filter(|(key, _)| applied.contains_key(key))
+        .collect();
+
+    let expected = data_hash(applied);
+    let actual = data_hash(&data);
+    if actual != expected {
+        anyhow::bail!(
+            "Secret '{}' in namespace '{}' does not match what was applied (sha256 {} expected, got {})",
+            name,
+            namespace,
+            expected,
+            actual
+        );
+    }
+
+    Ok(())
+}
+
+/// Resolve a secret type, expanding short names (e.g. `tls`) to the full
+/// Kubernetes type. Unknown values are passed through unchanged.
+fn resolve_secret_type(secret_type: &str) -> String {
+    match secret_type.to_ascii_lowercase().as_str() {
+        "opaque" => "Opaque",
+        "tls" => "kubernetes.io/tls",
+        "dockerconfigjson" => "kubernetes.io/dockerconfigjson",
+        "basic-auth" => "kubernetes.io/basic-auth",
+        "ssh-auth" => "kubernetes.io/ssh-auth",
+        _ => secret_type,
+    }
+    .to_string()
+}
+
+/// Check that the data carries the keys Kubernetes requires for the type
+fn validate_secret_type(secret_type: &str, data: &BTreeMap<String, Vec<u8>>) -> Result<()> {
+    let required: &[&str] = match secret_type {
+        "kubernetes.io/tls" => &["tls.crt", "tls.key"],
+        "kubernetes.io/dockerconfigjson" => &[".dockerconfigjson"],
+        _ => &[],
+    };
+
+    let missing: Vec<&str> = required
+        .iter()
+        .filter(|k| !data.contains_key(**k))
+        .copied()
+        .collect();
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "Secrets of type '{}' require the key(s) {}, but they are missing (found: {})",
+            secret_type,
+            missing.join(", "),
+            data.keys().cloned().collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Labels recording which environment and prefix a synced secret came from
+fn source_labels(config: &Config, environment: &str) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        (ENVIRONMENT_LABEL.to_string(), environment.to_string()),
+        (PREFIX_LABEL.to_string(), config.secret_prefix.clone()),
+    ])
+}
+
+/// Delete ksecret-managed secrets that aren't in `known` for their namespace.
+///
+/// Only secrets carrying our managed-by label and the `source` labels of this
+/// environment and prefix are considered, so unmanaged resources and secrets
+/// synced from other environments are never touched. Unless `assume_yes` is
+/// set, the stale secrets are listed and the user is asked to confirm first.
+async fn prune_namespaces(
+    k8s_client: &KubeClient,
+    known: &BTreeMap<String, Vec<String>>,
+    source: &BTreeMap<String, String>,
+    dry_run: bool,
+    assume_yes: bool,
+) -> Result<()> {
+    let mut stale: Vec<(&str, String)> = Vec::new();
+    for (namespace, names) in known {
+        for name in k8s_client.list_managed_secrets(namespace, source).await? {
+            if !names.contains(&name) {
+                stale.push((namespace, name));
+            }
+        }
+    }
+
+    if stale.is_empty() {
+        return Ok(());
+    }
+
+    status!(
+        "  Pruning {} stale secret(s)",
+        stale.len().to_string().yellow()
     );
 
+    if !dry_run && !assume_yes && !confirm_prune(&stale)? {
+        status!("{}", "  Prune aborted.".yellow());
+        return Ok(());
+    }
+
+    for (namespace, name) in &stale {
+        status_inline!("  {} {}/{}... ", "-".red(), namespace, name);
+
+        if dry_run {
+            status!("{}", "would prune (dry-run)".yellow());
+            continue;
+        }
+
+        k8s_client
+            .delete_secret(namespace, name)
+            .await
+            .with_context(|| format!("Failed to prune secret: {}", name))?;
+
+        status!("{}", "pruned".red());
+    }
+
     Ok(())
 }
+
+/// Ask whether the listed secrets may be pruned. Without a terminal to ask on,
+/// pruning is refused instead of waiting on stdin.
+fn confirm_prune(stale: &[(&str, String)]) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        anyhow::bail!(
+            "Refusing to prune {} secret(s) without confirmation; pass --yes to prune non-interactively",
+            stale.len()
+        );
+    }
+
+    for (namespace, name) in stale {
+        println!("    {}/{}", namespace, name);
+    }
+    print!(
+        "{} Delete these {} secret(s) from Kubernetes? [y/N] ",
+        "?".yellow().bold(),
+        stale.len()
+    );
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+
+    Ok(input.trim().eq_ignore_ascii_case("y"))
+}
+
+/// Parse `key=value` arguments for Kubernetes labels or annotations
+fn parse_key_values(args: &[String], kind: &str) -> Result<BTreeMap<String, String>> {
+    args.iter()
+        .map(|arg| match arg.split_once('=') {
+            Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+            _ => anyhow::bail!("Invalid {} '{}': expected key=value", kind, arg),
+        })
+        .collect()
+}
+
+/// Apply the --only / --except filters to the listed secrets
+fn filter_secrets(
+    secrets: Vec<SecretInfo>,
+    only: &[String],
+    except: &[String],
+) -> Result<Vec<SecretInfo>> {
+    if !only.is_empty() {
+        let unknown: Vec<&str> = only
+            .iter()
+            .filter(|name| !secrets.iter().any(|s| &s.name == *name))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            anyhow::bail!("Unknown secret(s) in --only: {}", unknown.join(", "));
+        }
+        return Ok(secrets
+            .into_iter()
+            .filter(|s| only.contains(&s.name))
+            .collect());
+    }
+
+    Ok(secrets
+        .into_iter()
+        .filter(|s| !except.contains(&s.name))
+        .collect())
+}
+
+/// Build the Kubernetes secret data for a GCP secret value.
+///
+/// Multi-value (JSON/YAML) secrets are expanded into keys, anything else
+/// (including binary data) is stored under a single "value" key.
+pub fn secret_data(value: Vec<u8>) -> BTreeMap<String, Vec<u8>> {
+    std::str::from_utf8(&value)
+        .ok()
+        .and_then(parse_multi_value)
+        .unwrap_or_else(|| BTreeMap::from([("value".to_string(), value)]))
+}
+
+/// Like [`secret_data`], but nested objects and arrays of a multi-value secret
+/// are expanded into `PARENT<separator>CHILD` keys (arrays by index) instead
+/// of being stored as JSON strings.
+pub fn flattened_secret_data(value: Vec<u8>, separator: &str) -> BTreeMap<String, Vec<u8>> {
+    let parsed = std::str::from_utf8(&value).ok().and_then(|text| {
+        serde_json::from_str::<serde_json::Value>(text)
+            .ok()
+            .or_else(|| serde_yaml::from_str::<serde_json::Value>(text).ok())
+    });
+
+    match parsed {
+        Some(serde_json::Value::Object(map)) if !map.is_empty() => {
+            let mut data = BTreeMap::new();
+            for (k, v) in map {
+                flatten_value(k, v, separator, &mut data);
+            }
+            data
+        }
+        _ => secret_data(value),
+    }
+}
+
+/// Insert `value` under `key`, recursing into objects and arrays
+fn flatten_value(
+    key: String,
+    value: serde_json::Value,
+    separator: &str,
+    data: &mut BTreeMap<String, Vec<u8>>,
+) {
+    match value {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            for (k, v) in map {
+                flatten_value(format!("{}{}{}", key, separator, k), v, separator, data);
+            }
+        }
+        serde_json::Value::Array(items) if !items.is_empty() => {
+            for (i, v) in items.into_iter().enumerate() {
+                flatten_value(format!("{}{}{}", key, separator, i), v, separator, data);
+            }
+        }
+        serde_json::Value::String(s) => {
+            data.insert(key, s.into_bytes());
+        }
+        // Scalars and empty containers keep their JSON representation
+        other => {
+            data.insert(key, other.to_string().into_bytes());
+        }
+    }
+}
+
+/// Parse a multi-value secret (a JSON object or YAML mapping) into its keys.
+///
+/// Returns `None` when the value is a plain single value.
+pub fn parse_multi_value(value: &str) -> Option<BTreeMap<String, Vec<u8>>> {
+    let mut data = BTreeMap::new();
+
+    // Try parsing as JSON Object first
+    if let Ok(serde_json::Value::Object(map)) = serde_json::from_str::<serde_json::Value>(value) {
+        for (k, v) in map {
+            let v_str = match v {
+                serde_json::Value::String(s) => s,
+                _ => v.to_string(),
+            };
+            data.insert(k, v_str.into_bytes());
+        }
+    }
+    // If not JSON object, try parsing as YAML Mapping
+    else if let Ok(serde_yaml::Value::Mapping(map)) = serde_yaml::from_str::<serde_yaml::Value>(value) {
+        for (k, v) in map {
+            if let Some(k_str) = k.as_str() {
+                let v_str = match v {
+                    serde_yaml::Value::String(s) => s,
+                    serde_yaml::Value::Bool(b) => b.to_string(),
+                    serde_yaml::Value::Number(n) => n.to_string(),
+                    _ => {
+                        // Serialize complex types back to string, trimming the newline usually added by to_string
+                        serde_yaml::to_string(&v)
+                            .unwrap_or_default()
+                            .trim()
+                            .to_string()
+                    }
+                };
+                data.insert(k_str.to_string(), v_str.into_bytes());
+            }
+        }
+    }
+
+    // Parsing failed or didn't yield any keys
+    if data.is_empty() {
+        None
+    } else {
+        Some(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn flatten(value: serde_json::Value) -> BTreeMap<String, String> {
+        let mut data = BTreeMap::new();
+        flatten_value("db".to_string(), value, "_", &mut data);
+        data.into_iter()
+            .map(|(k, v)| (k, String::from_utf8(v).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn flatten_value_joins_two_levels_of_nesting() {
+        let data = flatten(json!({
+            "primary": { "host": "db-1", "port": 5432 },
+            "user": "app",
+        }));
+
+        assert_eq!(
+            data,
+            BTreeMap::from([
+                ("db_primary_host".to_string(), "db-1".to_string()),
+                ("db_primary_port".to_string(), "5432".to_string()),
+                ("db_user".to_string(), "app".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn flatten_value_indexes_arrays() {
+        let data = flatten(json!({ "hosts": ["a", { "name": "b" }], "tags": [] }));
+
+        assert_eq!(
+            data,
+            BTreeMap::from([
+                ("db_hosts_0".to_string(), "a".to_string()),
+                ("db_hosts_1_name".to_string(), "b".to_string()),
+                ("db_tags".to_string(), "[]".to_string()),
+            ])
+        );
+    }
+}
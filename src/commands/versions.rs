@@ -0,0 +1,79 @@
+use anyhow::Result;
+use colored::Colorize;
+use serde_json::json;
+
+use crate::config::Config;
+use crate::gcp::SecretManagerClient;
+
+pub async fn execute(config: &Config, name: &str, env: &str, output: &str) -> Result<()> {
+    config.validate_secret_id(env, name)?;
+
+    let gcp_client = SecretManagerClient::new(config.clone()).await?;
+    let versions = gcp_client.list_secret_versions(env, name).await?;
+
+    match output {
+        "json" => {
+            let output: Vec<_> = versions
+                .iter()
+                .map(|v| {
+                    json!({
+                        "version": v.version,
+                        "state": v.state,
+                        "created_at": v.created_at
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+        _ => {
+            if versions.is_empty() {
+                println!(
+                    "{} No versions found for secret '{}' in environment '{}'",
+                    "!".yellow().bold(),
+                    name,
+                    env
+                );
+                return Ok(());
+            }
+
+            println!(
+                "{} Versions of secret '{}' in environment '{}':\n",
+                "->".blue().bold(),
+                name.cyan(),
+                env.cyan()
+            );
+
+            println!(
+                "  {:<10} {:<12} {:<20}",
+                "VERSION".bold(),
+                "STATE".bold(),
+                "CREATED".bold()
+            );
+            println!("  {}", "-".repeat(44));
+
+            for version in &versions {
+                let state = match version.state.as_str() {
+                    "ENABLED" => version.state.green(),
+                    "DISABLED" => version.state.yellow(),
+                    "DESTROYED" => version.state.red(),
+                    _ => version.state.normal(),
+                };
+                let created = version
+                    .created_at
+                    .as_deref()
+                    .unwrap_or("-")
+                    .chars()
+                    .take(19)
+                    .collect::<String>();
+                println!("  {:<10} {:<12} {:<20}", version.version, state, created);
+            }
+
+            println!(
+                "\n  Total: {} version(s)",
+                versions.len().to_string().green()
+            );
+        }
+    }
+
+    Ok(())
+}
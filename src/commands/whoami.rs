@@ -0,0 +1,41 @@
+use anyhow::Result;
+use colored::Colorize;
+use serde_json::json;
+
+use crate::config::Config;
+use crate::gcp::identity;
+
+pub async fn execute(config: &Config, output: &str) -> Result<()> {
+    let token = identity::access_token(config).await?;
+    let principal = identity::principal(config, &token).await?;
+    let impersonating = config.impersonate_service_account.as_deref();
+
+    match output {
+        "json" => {
+            let document = json!({
+                "principal": principal,
+                "project": config.gcp_project_id,
+                "impersonate_service_account": impersonating
+            });
+            println!("{}", serde_json::to_string_pretty(&document)?);
+        }
+        _ => {
+            println!("{} GCP identity\n", "->".blue().bold());
+            println!(
+                "  {:<14} {}",
+                "Principal:".bold(),
+                principal.as_deref().unwrap_or("unknown").cyan()
+            );
+            println!(
+                "  {:<14} {}",
+                "Project:".bold(),
+                config.gcp_project_id.cyan()
+            );
+            if let Some(email) = impersonating {
+                println!("  {:<14} {}", "Impersonating:".bold(), email.cyan());
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,25 +1,182 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Deref;
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// Application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     /// Google Cloud Project ID
+    #[serde(default)]
     pub gcp_project_id: String,
 
+    /// Use gcloud's active project when no project is configured or given
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub gcp_project_from_gcloud: bool,
+
     /// Secret name prefix (default: "k8s")
     #[serde(default = "default_prefix")]
     pub secret_prefix: String,
+
+    /// Regions for user-managed replication (automatic replication when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replication_locations: Option<Vec<String>>,
+
+    /// Lifetime of locally cached secret values in seconds (default: 300)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_ttl_seconds: Option<i64>,
+
+    /// Lifetime of cached secret listings in seconds (default: 60)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_list_ttl_seconds: Option<i64>,
+
+    /// Versions pinned during sync, keyed by environment then secret name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub pinned_versions: BTreeMap<String, BTreeMap<String, String>>,
+
+    /// Per-environment overrides of the project and prefix, keyed by
+    /// environment
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub environments: BTreeMap<String, EnvironmentConfig>,
+
+    /// Timeout in seconds for each GCP / Kubernetes operation (default: none)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+
+    /// Seconds to wait for a connection to the Kubernetes API server
+    /// (default: 30)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub k8s_connect_timeout_seconds: Option<u64>,
+
+    /// Seconds to wait on a Kubernetes API request's response (default: 295)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub k8s_request_timeout_seconds: Option<u64>,
+
+    /// Cloud KMS key used to encrypt secret payloads of newly created secrets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kms_key_name: Option<String>,
+
+    /// Service account to impersonate for all GCP calls
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonate_service_account: Option<String>,
+
+    /// Always use the in-cluster service account for Kubernetes, ignoring any
+    /// kubeconfig
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub in_cluster: bool,
+
+    /// Extra labels added to every synced Kubernetes secret
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub k8s_labels: BTreeMap<String, String>,
+
+    /// Extra annotations added to every synced Kubernetes secret
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub k8s_annotations: BTreeMap<String, String>,
+
+    /// Destroy the oldest enabled versions beyond this many after `set`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_versions: Option<usize>,
+
+    /// Secrets fetched per page when listing (default: 100)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gcp_page_size: Option<i32>,
+
+    /// Name of synced Kubernetes secrets, with a `{name}` placeholder for the
+    /// GCP secret name (default: "{name}")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub k8s_secret_name_template: Option<String>,
+
+    /// Kubernetes data key for each detected content type with `sync
+    /// --smart-keys`, overriding the conventional ones
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub content_type_keys: BTreeMap<String, String>,
 }
 
+/// Settings of one `[environments.<env>]` table; unset keys fall back to the
+/// top-level ones
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EnvironmentConfig {
+    /// Google Cloud Project ID holding this environment's secrets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gcp_project_id: Option<String>,
+
+    /// Secret name prefix for this environment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_prefix: Option<String>,
+}
+
+/// Configuration of a single environment: the base config with that
+/// environment's overrides merged in
+#[derive(Debug, Clone)]
+pub struct ResolvedConfig {
+    config: Config,
+}
+
+impl ResolvedConfig {
+    /// The merged config, e.g. to build a client from
+    pub fn into_config(self) -> Config {
+        self.config
+    }
+}
+
+impl Deref for ResolvedConfig {
+    type Target = Config;
+
+    fn deref(&self) -> &Config {
+        &self.config
+    }
+}
+
+/// Maximum length of a GCP Secret Manager secret id
+const MAX_SECRET_ID_LEN: usize = 255;
+/// Project-local config file, searched for upward from the current directory
+const LOCAL_CONFIG_FILE: &str = ".ksecret.toml";
+/// Secrets fetched per page when listing, unless configured
+const DEFAULT_PAGE_SIZE: i32 = 100;
+/// Largest page size Secret Manager accepts
+const MAX_PAGE_SIZE: i32 = 25_000;
+/// Longest name Kubernetes allows for a secret (a DNS subdomain)
+const MAX_K8S_NAME_LEN: usize = 253;
+/// Longest cache lifetime accepted (30 days); far larger values would overflow
+/// the expiry timestamps
+const MAX_CACHE_TTL_SECONDS: i64 = 30 * 24 * 60 * 60;
+
 fn default_prefix() -> String {
     "k8s".to_string()
 }
 
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            gcp_project_id: String::new(),
+            gcp_project_from_gcloud: false,
+            secret_prefix: default_prefix(),
+            replication_locations: None,
+            cache_ttl_seconds: None,
+            cache_list_ttl_seconds: None,
+            pinned_versions: BTreeMap::new(),
+            environments: BTreeMap::new(),
+            timeout_seconds: None,
+            k8s_connect_timeout_seconds: None,
+            k8s_request_timeout_seconds: None,
+            kms_key_name: None,
+            impersonate_service_account: None,
+            in_cluster: false,
+            k8s_labels: BTreeMap::new(),
+            k8s_annotations: BTreeMap::new(),
+            max_versions: None,
+            gcp_page_size: None,
+            k8s_secret_name_template: None,
+            content_type_keys: BTreeMap::new(),
+        }
+    }
+}
+
 impl Config {
     /// Load configuration from file and environment
-    pub fn load(project_override: Option<String>) -> Result<Self> {
+    pub fn load(project_override: Option<String>, prefix_override: Option<String>) -> Result<Self> {
         let config_path = Self::config_path()?;
 
         let mut config = if config_path.exists() {
@@ -31,23 +188,57 @@ impl Config {
             // Return error if no config and no override
             if project_override.is_none() {
                 anyhow::bail!(
-                    "No configuration found. Run 'ksecret init --project <PROJECT_ID>' to initialize."
+                    "No configuration found. Looked for, in order:\n  \
+                     1. the file named by KSECRET_CONFIG_FILE\n  \
+                     2. {} in the current directory or any parent\n  \
+                     3. {}\n\
+                     Run 'ksecret init --project <PROJECT_ID>' to initialize.",
+                    LOCAL_CONFIG_FILE,
+                    Self::user_config_path()?.display()
                 );
             }
-            Config {
-                gcp_project_id: String::new(),
-                secret_prefix: default_prefix(),
-            }
+            Config::default()
         };
 
-        // Apply override if provided
-        if let Some(project) = project_override {
-            config.gcp_project_id = project;
+        config
+            .validate_cache_ttls()
+            .with_context(|| format!("Invalid config file: {:?}", config_path))?;
+        for (environment, overrides) in &config.environments {
+            validate_id_part("Environment", environment)?;
+            if let Some(prefix) = &overrides.secret_prefix {
+                validate_id_part("Secret prefix", prefix)
+                    .with_context(|| format!("Invalid [environments.{}] in config", environment))?;
+            }
         }
 
+        config.apply_overrides(project_override, prefix_override)?;
         Ok(config)
     }
 
+    /// Apply `--project` / `--prefix`; they win over per-environment settings
+    fn apply_overrides(
+        &mut self,
+        project_override: Option<String>,
+        prefix_override: Option<String>,
+    ) -> Result<()> {
+        if let Some(project) = project_override {
+            self.gcp_project_id = project;
+            for overrides in self.environments.values_mut() {
+                overrides.gcp_project_id = None;
+            }
+        } else if self.gcp_project_id.trim().is_empty() && self.gcp_project_from_gcloud {
+            self.gcp_project_id = gcloud_project()?;
+        }
+        if let Some(prefix) = prefix_override {
+            validate_id_part("Secret prefix", &prefix)?;
+            self.secret_prefix = prefix;
+            for overrides in self.environments.values_mut() {
+                overrides.secret_prefix = None;
+            }
+        }
+        Ok(())
+    }
+
     /// Save configuration to file
     pub fn save(&self) -> Result<()> {
         let config_path = Self::config_path()?;
@@ -65,23 +256,207 @@ impl Config {
         Ok(())
     }
 
-    /// Get the configuration file path
+    /// Get the configuration file path.
+    ///
+    /// `KSECRET_CONFIG_FILE` wins, then the nearest `.ksecret.toml` in the
+    /// current directory or its parents, then the per-user config.
     pub fn config_path() -> Result<PathBuf> {
         if let Ok(path) = std::env::var("KSECRET_CONFIG_FILE") {
             return Ok(PathBuf::from(path));
         }
+        if let Some(path) = Self::local_config_path() {
+            return Ok(path);
+        }
+        Self::user_config_path()
+    }
+
+    /// Per-user config file in the home directory
+    fn user_config_path() -> Result<PathBuf> {
         let home = dirs::home_dir().context("Could not determine home directory")?;
         let config_dir = home.join(".config").join("ksecret");
         Ok(config_dir.join("config.toml"))
     }
 
+    /// Nearest project-local config file, if any
+    fn local_config_path() -> Option<PathBuf> {
+        let cwd = std::env::current_dir().ok()?;
+        cwd.ancestors()
+            .map(|dir| dir.join(LOCAL_CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Config for one environment, with its `[environments.<env>]` project and
+    /// prefix (if any) in place of the top-level ones
+    pub fn for_environment(&self, environment: &str) -> ResolvedConfig {
+        let mut config = self.clone();
+        if let Some(overrides) = self.environments.get(environment) {
+            if let Some(project) = overrides
+                .gcp_project_id
+                .as_ref()
+                .filter(|project| !project.trim().is_empty())
+            {
+                config.gcp_project_id = project.clone();
+            }
+            if let Some(prefix) = &overrides.secret_prefix {
+                config.secret_prefix = prefix.clone();
+            }
+        }
+        ResolvedConfig { config }
+    }
+
+    /// Environments configured with a project or prefix other than the
+    /// top-level one. Their secrets don't show up when listing under the base
+    /// project and prefix.
+    pub fn overridden_environments(&self) -> Vec<String> {
+        self.environments
+            .keys()
+            .filter(|environment| {
+                let resolved = self.for_environment(environment);
+                resolved.gcp_project_id != self.gcp_project_id
+                    || resolved.secret_prefix != self.secret_prefix
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Per-operation timeout for GCP and Kubernetes calls
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_seconds.map(Duration::from_secs)
+    }
+
+    /// Connect timeout for the Kubernetes API server
+    pub fn k8s_connect_timeout(&self) -> Option<Duration> {
+        self.k8s_connect_timeout_seconds.map(Duration::from_secs)
+    }
+
+    /// Read / write timeout for Kubernetes API requests
+    pub fn k8s_request_timeout(&self) -> Option<Duration> {
+        self.k8s_request_timeout_seconds.map(Duration::from_secs)
+    }
+
+    /// Page size for listing secrets, clamped to what GCP accepts
+    pub fn page_size(&self) -> i32 {
+        self.gcp_page_size
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+
+    /// Check that the project id is set, the secret prefix only uses
+    /// characters GCP allows in a secret id and the cache lifetimes are sane
+    pub fn validate(&self) -> Result<()> {
+        if self.gcp_project_id.trim().is_empty() {
+            anyhow::bail!("GCP project ID must not be empty");
+        }
+        validate_id_part("Secret prefix", &self.secret_prefix)?;
+        self.validate_cache_ttls()
+    }
+
+    /// Check that the cache lifetimes are positive and at most 30 days
+    fn validate_cache_ttls(&self) -> Result<()> {
+        for (setting, seconds) in [
+            ("cache_ttl_seconds", self.cache_ttl_seconds),
+            ("cache_list_ttl_seconds", self.cache_list_ttl_seconds),
+        ] {
+            if let Some(seconds) = seconds {
+                if !(1..=MAX_CACHE_TTL_SECONDS).contains(&seconds) {
+                    anyhow::bail!(
+                        "{} must be between 1 and {} (30 days), got {}",
+                        setting,
+                        MAX_CACHE_TTL_SECONDS,
+                        seconds
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that a secret prefix only uses characters GCP allows in a secret id
+    pub fn validate_prefix(prefix: &str) -> Result<()> {
+        validate_id_part("Secret prefix", prefix)
+    }
+
+    /// Check that an environment name only uses characters GCP allows in a
+    /// secret id
+    pub fn validate_environment(&self, environment: &str) -> Result<()> {
+        validate_id_part("Environment", environment)
+    }
+
+    /// Check that a secret name is valid and that the full secret id built from
+    /// prefix, environment and name fits GCP's limits
+    pub fn validate_secret_id(&self, environment: &str, name: &str) -> Result<()> {
+        self.validate_environment(environment)?;
+        validate_id_part("Secret name", name)?;
+
+        let secret_id = self.build_secret_name(environment, name);
+        if secret_id.len() > MAX_SECRET_ID_LEN {
+            anyhow::bail!(
+                "Secret id '{}' is {} characters long (GCP allows at most {}); use a shorter secret name",
+                secret_id,
+                secret_id.len(),
+                MAX_SECRET_ID_LEN
+            );
+        }
+
+        Ok(())
+    }
+
     /// Build the full secret name for GCP Secret Manager
     pub fn build_secret_name(&self, environment: &str, name: &str) -> String {
         format!("{}-{}-{}", self.secret_prefix, environment, name)
     }
 
+    /// Name of the Kubernetes secret a GCP secret is synced to.
+    ///
+    /// `k8s_secret_name_template` replaces `{name}` with the secret name,
+    /// optionally passed through `|`-separated transforms: `lower` lowercases
+    /// it and `dashes` replaces `_` with `-` (e.g. `app-{name|lower|dashes}`).
+    /// The result must be a valid Kubernetes secret name.
+    pub fn k8s_secret_name(&self, name: &str) -> Result<String> {
+        let template = self.k8s_secret_name_template.as_deref().unwrap_or("{name}");
+
+        let mut rendered = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            rendered.push_str(&rest[..start]);
+            let end = rest[start..].find('}').with_context(|| {
+                format!("Unclosed '{{' in k8s_secret_name_template '{}'", template)
+            })? + start;
+
+            let mut directives = rest[start + 1..end].split('|');
+            if directives.next() != Some("name") {
+                anyhow::bail!(
+                    "Unknown placeholder '{}' in k8s_secret_name_template (only {{name}} is supported)",
+                    &rest[start..=end]
+                );
+            }
+            let mut value = name.to_string();
+            for directive in directives {
+                value = match directive.trim() {
+                    "lower" => value.to_lowercase(),
+                    "dashes" => value.replace('_', "-"),
+                    other => anyhow::bail!(
+                        "Unknown transform '{}' in k8s_secret_name_template (use lower or dashes)",
+                        other
+                    ),
+                };
+            }
+            rendered.push_str(&value);
+            rest = &rest[end + 1..];
+        }
+        rendered.push_str(rest);
+
+        validate_k8s_name(&rendered).with_context(|| {
+            format!(
+                "Secret '{}' can't be synced with k8s_secret_name_template '{}'",
+                name, template
+            )
+        })?;
+
+        Ok(rendered)
+    }
+
     /// Parse environment and name from a full GCP secret name
-    #[allow(dead_code)]
     pub fn parse_secret_name(&self, full_name: &str) -> Option<(String, String)> {
         let prefix = format!("{}-", self.secret_prefix);
         if !full_name.starts_with(&prefix) {
@@ -106,6 +481,15 @@ impl Config {
         )
     }
 
+    /// Get the version to sync for a secret ("latest" unless pinned)
+    pub fn pinned_version(&self, environment: &str, name: &str) -> &str {
+        self.pinned_versions
+            .get(environment)
+            .and_then(|versions| versions.get(name))
+            .map(String::as_str)
+            .unwrap_or("latest")
+    }
+
     /// Build the GCP Secret Manager version resource name
     pub fn build_version_name(&self, environment: &str, name: &str, version: &str) -> String {
         format!(
@@ -116,3 +500,223 @@ impl Config {
         )
     }
 }
+
+/// Validate one component of a secret id against `[a-zA-Z0-9_-]`
+fn validate_id_part(kind: &str, value: &str) -> Result<()> {
+    if value.is_empty() {
+        anyhow::bail!("{} must not be empty", kind);
+    }
+
+    if let Some((position, c)) = value
+        .chars()
+        .enumerate()
+        .find(|(_, c)| !(c.is_ascii_alphanumeric() || *c == '_' || *c == '-'))
+    {
+        anyhow::bail!(
+            "{} '{}' contains invalid character '{}' at position {} (only letters, digits, '_' and '-' are allowed)",
+            kind,
+            value,
+            c,
+            position + 1
+        );
+    }
+
+    if value.len() > MAX_SECRET_ID_LEN {
+        anyhow::bail!(
+            "{} '{}' is longer than {} characters",
+            kind,
+            value,
+            MAX_SECRET_ID_LEN
+        );
+    }
+
+    Ok(())
+}
+
+/// Check a Kubernetes secret name against the DNS subdomain rules: lowercase
+/// letters, digits, '-' and '.', starting and ending with a letter or digit
+fn validate_k8s_name(name: &str) -> Result<()> {
+    let is_alphanumeric = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+
+    if name.is_empty()
+        || name.len() > MAX_K8S_NAME_LEN
+        || !name.starts_with(is_alphanumeric)
+        || !name.ends_with(is_alphanumeric)
+        || !name
+            .chars()
+            .all(|c| is_alphanumeric(c) || c == '-' || c == '.')
+    {
+        anyhow::bail!(
+            "Invalid Kubernetes secret name '{}': use up to {} lowercase letters, digits, '-' or '.', starting and ending with a letter or digit",
+            name,
+            MAX_K8S_NAME_LEN
+        );
+    }
+
+    Ok(())
+}
+
+/// Active project of the gcloud CLI, as shown by `gcloud config get-value
+/// project`
+fn gcloud_project() -> Result<String> {
+    let output = std::process::Command::new("gcloud")
+        .args(["config", "get-value", "project"])
+        .output()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => anyhow::anyhow!(
+                "gcp_project_from_gcloud is set but gcloud is not installed (or not on PATH); set gcp_project_id or pass --project instead"
+            ),
+            _ => anyhow::Error::new(e).context("Failed to run gcloud"),
+        })?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "'gcloud config get-value project' failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let project = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if project.is_empty() || project == "(unset)" {
+        anyhow::bail!(
+            "gcloud has no active project; run 'gcloud config set project <PROJECT_ID>' or set gcp_project_id"
+        );
+    }
+
+    Ok(project)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config {
+            gcp_project_id: "my-project".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn config_with_ttl(seconds: i64) -> Config {
+        Config {
+            cache_ttl_seconds: Some(seconds),
+            ..config()
+        }
+    }
+
+    #[test]
+    fn validate_accepts_cache_ttl_in_range() {
+        assert!(config_with_ttl(1).validate().is_ok());
+        assert!(config_with_ttl(MAX_CACHE_TTL_SECONDS).validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_non_positive_cache_ttl() {
+        for seconds in [0, -1, i64::MIN] {
+            let err = config_with_ttl(seconds).validate().unwrap_err();
+            assert!(err.to_string().contains("cache_ttl_seconds"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn validate_rejects_huge_cache_ttl() {
+        for seconds in [MAX_CACHE_TTL_SECONDS + 1, i64::MAX] {
+            assert!(config_with_ttl(seconds).validate().is_err());
+        }
+    }
+
+    #[test]
+    fn validate_secret_id_accepts_letters_digits_dashes_and_underscores() {
+        assert!(config().validate_secret_id("dev", "db_password-2").is_ok());
+    }
+
+    #[test]
+    fn validate_secret_id_rejects_empty_name() {
+        let err = config().validate_secret_id("dev", "").unwrap_err();
+        assert_eq!(err.to_string(), "Secret name must not be empty");
+    }
+
+    #[test]
+    fn validate_secret_id_names_invalid_character_and_position() {
+        let err = config()
+            .validate_secret_id("dev", "db.password")
+            .unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.contains("invalid character '.' at position 3"),
+            "{}",
+            message
+        );
+
+        let err = config().validate_secret_id("dev", "app/db").unwrap_err();
+        let message = err.to_string();
+        assert!(
+            message.contains("invalid character '/' at position 4"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn validate_secret_id_rejects_invalid_environment() {
+        let err = config().validate_secret_id("dev.eu", "db").unwrap_err();
+        assert!(
+            err.to_string().starts_with("Environment 'dev.eu'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn validate_secret_id_rejects_overlong_id() {
+        let name = "a".repeat(MAX_SECRET_ID_LEN - "k8s-dev-".len() + 1);
+        let err = config().validate_secret_id("dev", &name).unwrap_err();
+        assert!(err.to_string().contains("characters long"), "{}", err);
+    }
+
+    fn config_with_prod_prefix() -> Config {
+        let mut config = config();
+        config.environments.insert(
+            "prod".to_string(),
+            EnvironmentConfig {
+                gcp_project_id: None,
+                secret_prefix: Some("legacy".to_string()),
+            },
+        );
+        config
+    }
+
+    #[test]
+    fn prefix_override_composes_into_secret_names() {
+        let mut config = config_with_prod_prefix();
+        config
+            .apply_overrides(None, Some("ci".to_string()))
+            .unwrap();
+
+        let prod = config.for_environment("prod");
+        assert_eq!(prod.build_secret_name("prod", "db"), "ci-prod-db");
+        assert_eq!(
+            prod.build_resource_name("prod", "db"),
+            "projects/my-project/secrets/ci-prod-db"
+        );
+        assert_eq!(config.build_secret_name("dev", "db"), "ci-dev-db");
+    }
+
+    #[test]
+    fn without_prefix_override_environment_prefix_applies() {
+        let mut config = config_with_prod_prefix();
+        config.apply_overrides(None, None).unwrap();
+
+        let prod = config.for_environment("prod");
+        assert_eq!(prod.build_secret_name("prod", "db"), "legacy-prod-db");
+        assert_eq!(config.build_secret_name("dev", "db"), "k8s-dev-db");
+    }
+
+    #[test]
+    fn invalid_prefix_override_is_rejected() {
+        let mut config = config();
+        assert!(config
+            .apply_overrides(None, Some("ci.test".to_string()))
+            .is_err());
+    }
+}
@@ -0,0 +1,104 @@
+/// Kind of single-file content a secret value can hold, for `sync
+/// --smart-keys`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentType {
+    /// PEM certificate (`BEGIN CERTIFICATE`)
+    Certificate,
+    /// PEM private key (`BEGIN PRIVATE KEY`, `BEGIN RSA PRIVATE KEY`, ...)
+    PrivateKey,
+    /// OpenSSH private key (`BEGIN OPENSSH PRIVATE KEY`)
+    SshPrivateKey,
+    /// Kubeconfig file (`apiVersion`, `kind: Config` and `clusters`)
+    Kubeconfig,
+    /// GCP service account key (JSON with `"type": "service_account"`)
+    ServiceAccountKey,
+    /// Docker registry credentials (JSON with an `auths` object)
+    DockerConfig,
+}
+
+impl ContentType {
+    /// Every content type, in the order they're documented
+    pub const ALL: [ContentType; 6] = [
+        ContentType::Certificate,
+        ContentType::PrivateKey,
+        ContentType::SshPrivateKey,
+        ContentType::Kubeconfig,
+        ContentType::ServiceAccountKey,
+        ContentType::DockerConfig,
+    ];
+
+    /// Name used in the `ksecret-content` label and the `content_type_keys`
+    /// config
+    pub fn name(self) -> &'static str {
+        match self {
+            ContentType::Certificate => "certificate",
+            ContentType::PrivateKey => "private-key",
+            ContentType::SshPrivateKey => "ssh-private-key",
+            ContentType::Kubeconfig => "kubeconfig",
+            ContentType::ServiceAccountKey => "service-account",
+            ContentType::DockerConfig => "docker-config",
+        }
+    }
+
+    /// Conventional Kubernetes data key for this content
+    pub fn default_key(self) -> &'static str {
+        match self {
+            ContentType::Certificate => "tls.crt",
+            ContentType::PrivateKey => "tls.key",
+            ContentType::SshPrivateKey => "ssh-privatekey",
+            ContentType::Kubeconfig => "config",
+            ContentType::ServiceAccountKey => "credentials.json",
+            ContentType::DockerConfig => ".dockerconfigjson",
+        }
+    }
+
+    /// Look up a content type by its [`name`](ContentType::name)
+    pub fn from_name(name: &str) -> Option<ContentType> {
+        Self::ALL.into_iter().find(|content| content.name() == name)
+    }
+
+    /// Names of every content type, for error messages
+    pub fn names() -> String {
+        Self::ALL.map(ContentType::name).join(", ")
+    }
+}
+
+/// Guess what a secret value holds from its contents.
+///
+/// Only unambiguous single-file formats are recognized; plain values and
+/// ordinary JSON / YAML maps return `None` and keep their usual keys.
+pub fn detect(value: &[u8]) -> Option<ContentType> {
+    let text = std::str::from_utf8(value).ok()?.trim_start();
+
+    // PEM: the label on the first BEGIN line names what follows
+    if let Some(rest) = text.strip_prefix("-----BEGIN ") {
+        let label = &rest[..rest.find("-----")?];
+        return match label {
+            "CERTIFICATE" | "TRUSTED CERTIFICATE" => Some(ContentType::Certificate),
+            "OPENSSH PRIVATE KEY" => Some(ContentType::SshPrivateKey),
+            _ if label.ends_with("PRIVATE KEY") => Some(ContentType::PrivateKey),
+            _ => None,
+        };
+    }
+
+    if let Ok(serde_json::Value::Object(map)) = serde_json::from_str::<serde_json::Value>(text) {
+        if map.get("type").and_then(|t| t.as_str()) == Some("service_account") {
+            return Some(ContentType::ServiceAccountKey);
+        }
+        if map.get("auths").is_some_and(|auths| auths.is_object()) {
+            return Some(ContentType::DockerConfig);
+        }
+        return None;
+    }
+
+    if let Ok(serde_yaml::Value::Mapping(map)) = serde_yaml::from_str::<serde_yaml::Value>(text) {
+        let is_kubeconfig = map.contains_key("apiVersion")
+            && map.get("kind").and_then(|k| k.as_str()) == Some("Config")
+            && map.contains_key("clusters");
+        if is_kubeconfig {
+            return Some(ContentType::Kubeconfig);
+        }
+    }
+
+    None
+}
@@ -0,0 +1,79 @@
+/// Process exit code for an invalid command line (`EX_USAGE` from sysexits.h),
+/// kept apart from the codes of [`ErrorKind`]
+pub const USAGE_EXIT_CODE: i32 = 64;
+
+/// Broad class of an API error, used to pick the process exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The secret, namespace or other resource doesn't exist
+    NotFound,
+    /// The caller is authenticated but not allowed to do this
+    PermissionDenied,
+    /// The caller's credentials are missing or invalid
+    Unauthenticated,
+    /// Anything else
+    Other,
+}
+
+impl ErrorKind {
+    /// Classify a GCP status code name (e.g. "PermissionDenied")
+    pub fn from_gcp_code(code: &str) -> Self {
+        match code {
+            "NotFound" => Self::NotFound,
+            "PermissionDenied" => Self::PermissionDenied,
+            "Unauthenticated" => Self::Unauthenticated,
+            _ => Self::Other,
+        }
+    }
+
+    /// Classify a Kubernetes HTTP status code
+    pub fn from_http_status(status: u16) -> Self {
+        match status {
+            404 => Self::NotFound,
+            403 => Self::PermissionDenied,
+            401 => Self::Unauthenticated,
+            _ => Self::Other,
+        }
+    }
+
+    /// Process exit code for errors of this kind
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::NotFound => 2,
+            Self::PermissionDenied => 3,
+            Self::Unauthenticated => 4,
+            Self::Other => 1,
+        }
+    }
+
+    /// Name used in JSON error output
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::NotFound => "not_found",
+            Self::PermissionDenied => "permission_denied",
+            Self::Unauthenticated => "unauthenticated",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// An error reported by the GCP or Kubernetes API, carrying a user-facing hint
+/// as its message and the status code the service returned
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct ApiError {
+    /// Service that reported the error ("gcp" or "kubernetes")
+    pub service: &'static str,
+    /// Status code as reported by the service (e.g. "PermissionDenied", "403")
+    pub code: String,
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+/// Kind of the first API error in the chain, or `Other` if there is none
+pub fn error_kind(err: &anyhow::Error) -> ErrorKind {
+    err.chain()
+        .find_map(|e| e.downcast_ref::<ApiError>())
+        .map(|e| e.kind)
+        .unwrap_or(ErrorKind::Other)
+}
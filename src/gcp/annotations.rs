@@ -0,0 +1,56 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// Maximum length of an annotation key
+const MAX_KEY_LENGTH: usize = 63;
+/// Maximum total size of annotation keys and values on a secret
+const MAX_TOTAL_SIZE: usize = 16 * 1024;
+
+/// Parse `key=value` annotation arguments into a validated map.
+///
+/// Unlike labels, annotation values are free-form text (e.g. ownership notes
+/// or ticket links); only the keys and the total size are restricted.
+pub fn parse_annotations(args: &[String]) -> Result<BTreeMap<String, String>> {
+    let mut annotations = BTreeMap::new();
+
+    for arg in args {
+        let (key, value) = arg
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Invalid annotation '{}': expected key=value", arg))?;
+        validate_annotation_key(key)?;
+        annotations.insert(key.to_string(), value.to_string());
+    }
+
+    let total: usize = annotations.iter().map(|(k, v)| k.len() + v.len()).sum();
+    if total >= MAX_TOTAL_SIZE {
+        anyhow::bail!(
+            "Annotations too large: GCP allows less than {} KiB of keys and values per secret",
+            MAX_TOTAL_SIZE / 1024
+        );
+    }
+
+    Ok(annotations)
+}
+
+/// Validate an annotation key against GCP's constraints.
+///
+/// Keys start and end with a letter or digit and may contain letters, digits,
+/// '-', '_' and '.' in between, up to 63 characters.
+pub fn validate_annotation_key(key: &str) -> Result<()> {
+    let is_allowed = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.';
+
+    if key.is_empty()
+        || key.len() > MAX_KEY_LENGTH
+        || !key.starts_with(|c: char| c.is_ascii_alphanumeric())
+        || !key.ends_with(|c: char| c.is_ascii_alphanumeric())
+        || !key.chars().all(is_allowed)
+    {
+        anyhow::bail!(
+            "Invalid annotation key '{}': use up to {} letters, digits, '-', '_' or '.', starting and ending with a letter or digit",
+            key,
+            MAX_KEY_LENGTH
+        );
+    }
+
+    Ok(())
+}
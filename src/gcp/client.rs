@@ -1,44 +1,188 @@
 use crate::config::Config;
-use crate::gcp::error::map_gcp_error;
+use crate::error::{error_kind, ErrorKind};
+use crate::gcp::error::{is_already_exists, is_conflict, is_failed_precondition, map_gcp_error};
+use crate::timeout::TimeoutExt;
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use google_cloud_auth::credentials::{self, impersonated};
+use google_cloud_gax::options::RequestOptionsBuilder;
+use google_cloud_gax::retry_policy::{Aip194Strict, RetryPolicyExt};
+use google_cloud_iam_v1::model::{Binding, Policy};
 use google_cloud_secretmanager_v1::client::SecretManagerService;
+use google_cloud_secretmanager_v1::model::{
+    replication, CustomerManagedEncryption, Replication, Secret,
+};
+use google_cloud_wkt::{FieldMask, Timestamp};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// How often an IAM policy update is retried after a concurrent modification
+const MAX_IAM_RETRIES: usize = 5;
+/// Attempts per page when listing secrets, so a transient failure doesn't
+/// restart the whole listing
+const MAX_LIST_PAGE_ATTEMPTS: u32 = 3;
+/// Largest secret payload Secret Manager accepts, in bytes
+pub const MAX_SECRET_SIZE: usize = 64 * 1024;
 
 /// Wrapper around Google Cloud Secret Manager client
 pub struct SecretManagerClient {
     client: SecretManagerService,
     config: Config,
+    timeout: Option<Duration>,
 }
 
 /// Represents a secret retrieved from GCP
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecretInfo {
     pub name: String,
     pub environment: String,
     pub created_at: Option<String>,
+    /// Creation time in seconds since the Unix epoch, for sorting
+    pub created_timestamp: Option<i64>,
+    /// When GCP will delete the secret, if it has an expiration
+    pub expire_time: Option<String>,
+    pub labels: BTreeMap<String, String>,
+    /// Free-form annotations, separate from labels
+    pub annotations: BTreeMap<String, String>,
+}
+
+/// Represents a single version of a secret
+#[derive(Debug, Clone)]
+pub struct SecretVersionInfo {
+    pub version: String,
+    pub state: String,
+    pub created_at: Option<String>,
+    /// Creation time in seconds since the Unix epoch, for ordering
+    pub created_timestamp: Option<i64>,
+    pub destroyed_at: Option<String>,
+    /// Destruction time in seconds since the Unix epoch, for ordering
+    pub destroyed_timestamp: Option<i64>,
+}
+
+/// Project and impersonation target a Secret Manager service was built for
+type ServiceKey = (String, Option<String>);
+
+/// Services built so far in this process. Building one loads credentials and
+/// sets up a connection, so commands that create several clients (replicate,
+/// copy) reuse them instead.
+fn services() -> &'static Mutex<HashMap<ServiceKey, SecretManagerService>> {
+    static SERVICES: OnceLock<Mutex<HashMap<ServiceKey, SecretManagerService>>> = OnceLock::new();
+    SERVICES.get_or_init(Default::default)
 }
 
 impl SecretManagerClient {
-    /// Create a new Secret Manager client
+    /// Create a new Secret Manager client.
+    ///
+    /// Calls are made as `impersonate_service_account` when configured, using
+    /// the ambient credentials as the source. The underlying service is shared
+    /// with earlier clients for the same project and impersonation target.
     pub async fn new(config: Config) -> Result<Self> {
-        let client = SecretManagerService::builder()
+        let key = (
+            config.gcp_project_id.clone(),
+            config.impersonate_service_account.clone(),
+        );
+        let cached = services().lock().unwrap().get(&key).cloned();
+        let client = match cached {
+            Some(client) => client,
+            None => {
+                let client = Self::build_service(&config).await?;
+                // Two clients built concurrently both succeed; either one is fine
+                services().lock().unwrap().insert(key, client.clone());
+                client
+            }
+        };
+
+        let timeout = config.timeout();
+        Ok(Self {
+            client,
+            config,
+            timeout,
+        })
+    }
+
+    /// Build a Secret Manager service, impersonating the configured service
+    /// account if any
+    async fn build_service(config: &Config) -> Result<SecretManagerService> {
+        let mut builder = SecretManagerService::builder();
+
+        if let Some(email) = config.impersonate_service_account.as_deref() {
+            validate_service_account_email(email)?;
+
+            let source = credentials::Builder::default()
+                .build()
+                .context("Failed to load Google Cloud credentials")?;
+            let impersonated = impersonated::Builder::from_source_credentials(source)
+                .with_target_principal(email)
+                .build()
+                .with_context(|| format!("Failed to impersonate service account: {}", email))?;
+            builder = builder.with_credentials(impersonated);
+        }
+
+        builder
             .build()
             .await
             .map_err(|e| map_gcp_error(e.into()))
-            .context("Failed to create Secret Manager client")?;
-
-        Ok(Self { client, config })
+            .context("Failed to create Secret Manager client")
     }
 
     /// List all secrets for a given environment
     pub async fn list_secrets(&self, environment: &str) -> Result<Vec<SecretInfo>> {
+        let secrets = self.list_project_secrets().await?;
+        Ok(environment_secrets(&self.config, &secrets, environment))
+    }
+
+    /// List the secrets of every environment under the configured prefix
+    pub async fn list_all_secrets(&self) -> Result<Vec<SecretInfo>> {
+        let secrets = self
+            .list_project_secrets()
+            .await?
+            .iter()
+            .filter_map(|secret| {
+                let (environment, name) =
+                    self.config.parse_secret_name(short_name(&secret.name))?;
+                Some(secret_info(secret, &environment, &name))
+            })
+            .collect();
+
+        Ok(secrets)
+    }
+
+    /// Check that the project is reachable and its secrets may be listed, by
+    /// fetching a single one
+    pub async fn check_access(&self) -> Result<()> {
+        self.client
+            .list_secrets()
+            .set_parent(format!("projects/{}", self.config.gcp_project_id))
+            .set_page_size(1)
+            .send()
+            .with_timeout(self.timeout)
+            .await
+            .map_err(map_gcp_error)
+            .context("Failed to list secrets")?;
+        Ok(())
+    }
+
+    /// List every secret in the project, following pagination.
+    ///
+    /// Each page is retried on transient errors on its own.
+    #[tracing::instrument(name = "gcp.list_secrets", skip_all)]
+    async fn list_project_secrets(&self) -> Result<Vec<Secret>> {
         let parent = format!("projects/{}", self.config.gcp_project_id);
-        let prefix = format!("{}-{}-", self.config.secret_prefix, environment);
 
         let mut secrets = Vec::new();
+        let mut seen = HashSet::new();
         let mut page_token: Option<String> = None;
 
         loop {
-            let mut request = self.client.list_secrets().set_parent(&parent);
+            let mut request = self
+                .client
+                .list_secrets()
+                .set_parent(&parent)
+                .set_page_size(self.config.page_size())
+                .with_idempotency(true)
+                .with_retry_policy(Aip194Strict.with_attempt_limit(MAX_LIST_PAGE_ATTEMPTS));
 
             if let Some(token) = &page_token {
                 request = request.set_page_token(token);
@@ -46,31 +190,74 @@ impl SecretManagerClient {
 
             let response = request
                 .send()
+                .with_timeout(self.timeout)
                 .await
-                .map_err(|e| map_gcp_error(e.into()))
+                .map_err(map_gcp_error)
                 .context("Failed to list secrets")?;
 
-            for secret in response.secrets.iter() {
-                // Extract the secret name from the full resource path
-                let full_name = secret.name.as_str();
-                let short_name = full_name.rsplit('/').next().unwrap_or(full_name);
-
-                // Filter by environment prefix
-                if short_name.starts_with(&prefix) {
-                    let secret_name = short_name.strip_prefix(&prefix).unwrap_or(short_name);
-                    secrets.push(SecretInfo {
-                        name: secret_name.to_string(),
-                        environment: environment.to_string(),
-                        created_at: secret.create_time.as_ref().map(|t| {
-                            use chrono::{DateTime, Utc};
-                            let dt = DateTime::<Utc>::from_timestamp(t.seconds(), t.nanos() as u32);
-                            match dt {
-                                Some(d) => d.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
-                                None => "Unknown".to_string(),
-                            }
-                        }),
-                    });
-                }
+            // A retried page may overlap one already collected
+            secrets.extend(
+                response
+                    .secrets
+                    .into_iter()
+                    .filter(|secret| seen.insert(secret.name.clone())),
+            );
+
+            // Check for more pages; a repeated token would loop forever
+            if response.next_page_token.is_empty()
+                || page_token.as_deref() == Some(response.next_page_token.as_str())
+            {
+                break;
+            }
+            page_token = Some(response.next_page_token);
+        }
+
+        Ok(secrets)
+    }
+
+    /// List all versions of a secret, newest first
+    pub async fn list_secret_versions(
+        &self,
+        environment: &str,
+        name: &str,
+    ) -> Result<Vec<SecretVersionInfo>> {
+        let parent = self.config.build_resource_name(environment, name);
+
+        let mut versions = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let mut request = self.client.list_secret_versions().set_parent(&parent);
+
+            if let Some(token) = &page_token {
+                request = request.set_page_token(token);
+            }
+
+            let response = request
+                .send()
+                .with_timeout(self.timeout)
+                .await
+                .map_err(map_gcp_error)
+                .with_context(|| format!("Failed to list versions of secret: {}", name))?;
+
+            for version in response.versions.iter() {
+                let full_name = version.name.as_str();
+                let version_id = full_name.rsplit('/').next().unwrap_or(full_name);
+
+                versions.push(SecretVersionInfo {
+                    version: version_id.to_string(),
+                    state: version.state.name().unwrap_or("UNKNOWN").to_string(),
+                    created_at: version
+                        .create_time
+                        .as_ref()
+                        .map(|t| format_timestamp(t.seconds(), t.nanos())),
+                    created_timestamp: version.create_time.as_ref().map(|t| t.seconds()),
+                    destroyed_at: version
+                        .destroy_time
+                        .as_ref()
+                        .map(|t| format_timestamp(t.seconds(), t.nanos())),
+                    destroyed_timestamp: version.destroy_time.as_ref().map(|t| t.seconds()),
+                });
             }
 
             // Check for more pages
@@ -80,83 +267,485 @@ impl SecretManagerClient {
             page_token = Some(response.next_page_token.clone());
         }
 
-        Ok(secrets)
+        Ok(versions)
     }
 
-    /// Get a secret value
+    /// Get the latest secret value
     pub async fn get_secret(&self, environment: &str, name: &str) -> Result<String> {
-        let version_name = self.config.build_version_name(environment, name, "latest");
+        self.get_secret_version(environment, name, "latest").await
+    }
+
+    /// Get the latest secret value, or `None` if the secret (or any version of
+    /// it) doesn't exist. Every other error is returned.
+    pub async fn get_secret_opt(&self, environment: &str, name: &str) -> Result<Option<String>> {
+        match self.get_secret(environment, name).await {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if error_kind(&e) == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Get a specific secret version (a version number or "latest") as text
+    pub async fn get_secret_version(
+        &self,
+        environment: &str,
+        name: &str,
+        version: &str,
+    ) -> Result<String> {
+        let data = self
+            .get_secret_version_bytes(environment, name, version)
+            .await?;
+
+        String::from_utf8(data).with_context(|| {
+            format!(
+                "Secret '{}' contains binary data that is not valid UTF-8",
+                name
+            )
+        })
+    }
+
+    /// Get the latest secret value as raw bytes
+    pub async fn get_secret_bytes(&self, environment: &str, name: &str) -> Result<Vec<u8>> {
+        self.get_secret_version_bytes(environment, name, "latest")
+            .await
+    }
+
+    /// Get a specific secret version as raw bytes
+    pub async fn get_secret_version_bytes(
+        &self,
+        environment: &str,
+        name: &str,
+        version: &str,
+    ) -> Result<Vec<u8>> {
+        self.access_secret_version(environment, name, version)
+            .await
+            .map(|(_, data)| data)
+    }
+
+    /// Access a secret version, returning the version number it resolved to
+    /// (e.g. for "latest") along with its raw bytes
+    #[tracing::instrument(
+        name = "gcp.get_secret",
+        skip_all,
+        fields(environment = %environment, secret = %name, version = %version)
+    )]
+    pub async fn access_secret_version(
+        &self,
+        environment: &str,
+        name: &str,
+        version: &str,
+    ) -> Result<(String, Vec<u8>)> {
+        let version_name = self.config.build_version_name(environment, name, version);
 
         let response = self
             .client
             .access_secret_version()
             .set_name(&version_name)
             .send()
+            .with_timeout(self.timeout)
             .await
-            .map_err(|e| map_gcp_error(e.into()))
-            .with_context(|| format!("Failed to access secret: {}", name))?;
+            .map_err(map_gcp_error)
+            .with_context(|| format!("Failed to access secret: {} (version {})", name, version))?;
 
+        let resolved = short_name(&response.name).to_string();
         let payload = response.payload.context("Secret has no payload")?;
 
-        let data =
-            String::from_utf8(payload.data.to_vec()).context("Secret data is not valid UTF-8")?;
+        Ok((resolved, payload.data.to_vec()))
+    }
 
-        Ok(data)
+    /// Access the newest ENABLED version of a secret, returning its version
+    /// number and raw bytes
+    pub async fn get_latest_enabled(
+        &self,
+        environment: &str,
+        name: &str,
+    ) -> Result<(String, Vec<u8>)> {
+        let versions = self.list_secret_versions(environment, name).await?;
+        let newest = versions
+            .iter()
+            .filter(|v| v.state == "ENABLED")
+            .max_by_key(|v| v.version.parse::<u64>().unwrap_or(0))
+            .with_context(|| format!("Secret '{}' has no enabled versions", name))?;
+
+        self.access_secret_version(environment, name, &newest.version)
+            .await
+    }
+
+    /// Access a secret version like [`Self::access_secret_version`].
+    ///
+    /// With `resolve_enabled`, a `latest` version that is disabled or
+    /// destroyed falls back to the newest enabled version instead of failing.
+    /// Returns whether that fallback was used.
+    pub async fn access_secret_version_resolving(
+        &self,
+        environment: &str,
+        name: &str,
+        version: &str,
+        resolve_enabled: bool,
+    ) -> Result<(String, Vec<u8>, bool)> {
+        match self.access_secret_version(environment, name, version).await {
+            Ok((resolved, data)) => Ok((resolved, data, false)),
+            Err(e) if resolve_enabled && version == "latest" && is_failed_precondition(&e) => {
+                let (resolved, data) = self.get_latest_enabled(environment, name).await?;
+                tracing::debug!(
+                    secret = name,
+                    version = %resolved,
+                    "latest version is not enabled, fell back to newest enabled version"
+                );
+                Ok((resolved, data, true))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Check whether a secret exists. Only NotFound means it doesn't; any
+    /// other error (e.g. missing permissions) is returned.
+    pub async fn secret_exists(&self, environment: &str, name: &str) -> Result<bool> {
+        Ok(self.find_secret(environment, name).await?.is_some())
     }
 
     /// Create or update a secret
     pub async fn set_secret(&self, environment: &str, name: &str, value: &str) -> Result<()> {
-        let secret_id = self.config.build_secret_name(environment, name);
-        let parent = format!("projects/{}", self.config.gcp_project_id);
-        let secret_name = format!("{}/secrets/{}", parent, secret_id);
+        self.set_secret_bytes(
+            environment,
+            name,
+            value.as_bytes(),
+            &BTreeMap::new(),
+            &BTreeMap::new(),
+            None,
+        )
+        .await
+    }
+
+    /// Create or update a secret from raw bytes.
+    ///
+    /// `labels` and `annotations` are set when the secret is created and merged
+    /// into those of an existing secret. `expire_time`, when given, replaces
+    /// the secret's expiration.
+    pub async fn set_secret_bytes(
+        &self,
+        environment: &str,
+        name: &str,
+        value: &[u8],
+        labels: &BTreeMap<String, String>,
+        annotations: &BTreeMap<String, String>,
+        expire_time: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        // Checked before creating the secret so an oversized value doesn't
+        // leave an empty secret behind
+        validate_secret_size(name, value)?;
+
+        match self.find_secret(environment, name).await? {
+            None => {
+                self.create_secret(environment, name, labels, annotations, expire_time)
+                    .await?;
+            }
+            Some(mut secret) => {
+                let mut paths = Vec::new();
 
-        // Try to get the secret first to see if it exists
-        let secret_exists = self
+                let mut merged = secret.labels.clone();
+                merged.extend(labels.clone());
+                if merged != secret.labels {
+                    secret.labels = merged;
+                    paths.push("labels");
+                }
+
+                let mut merged = secret.annotations.clone();
+                merged.extend(annotations.clone());
+                if merged != secret.annotations {
+                    secret.annotations = merged;
+                    paths.push("annotations");
+                }
+
+                if let Some(time) = expire_time {
+                    secret = secret.set_expire_time(Timestamp::clamp(time.timestamp(), 0));
+                    paths.push("expire_time");
+                }
+
+                if !paths.is_empty() {
+                    self.client
+                        .update_secret()
+                        .set_secret(secret)
+                        .set_update_mask(FieldMask::default().set_paths(paths))
+                        .send()
+                        .with_timeout(self.timeout)
+                        .await
+                        .map_err(map_gcp_error)
+                        .with_context(|| format!("Failed to update secret: {}", name))?;
+                }
+            }
+        }
+
+        self.add_secret_version(environment, name, value).await?;
+
+        Ok(())
+    }
+
+    /// Metadata (labels, annotations, expiry) of a secret, or `None` if it
+    /// doesn't exist
+    pub async fn get_secret_info(
+        &self,
+        environment: &str,
+        name: &str,
+    ) -> Result<Option<SecretInfo>> {
+        let secret = self.find_secret(environment, name).await?;
+        Ok(secret.map(|secret| secret_info(&secret, environment, name)))
+    }
+
+    /// Create a secret with `value` as its first version, leaving an existing
+    /// secret untouched. Returns whether the secret was created.
+    pub async fn create_secret_bytes(
+        &self,
+        environment: &str,
+        name: &str,
+        value: &[u8],
+        labels: &BTreeMap<String, String>,
+        annotations: &BTreeMap<String, String>,
+        expire_time: Option<DateTime<Utc>>,
+    ) -> Result<bool> {
+        validate_secret_size(name, value)?;
+
+        if self.find_secret(environment, name).await?.is_some() {
+            return Ok(false);
+        }
+
+        match self
+            .create_secret(environment, name, labels, annotations, expire_time)
+            .await
+        {
+            Ok(()) => {}
+            // Created by someone else since the check
+            Err(e) if is_already_exists(&e) => return Ok(false),
+            Err(e) => return Err(e),
+        }
+
+        self.add_secret_version(environment, name, value).await?;
+
+        Ok(true)
+    }
+
+    /// Get a secret's metadata, or `None` if it doesn't exist. Any other error
+    /// (e.g. permission denied) is returned rather than treated as missing.
+    async fn find_secret(&self, environment: &str, name: &str) -> Result<Option<Secret>> {
+        let secret_name = self.config.build_resource_name(environment, name);
+
+        let found = self
             .client
             .get_secret()
             .set_name(&secret_name)
             .send()
+            .with_timeout(self.timeout)
             .await
-            .is_ok();
-
-        if !secret_exists {
-            let mut replication = google_cloud_secretmanager_v1::model::Replication::default();
-            replication.replication = Some(
-                google_cloud_secretmanager_v1::model::replication::Replication::Automatic(
-                    Box::new(
-                        google_cloud_secretmanager_v1::model::replication::Automatic::default(),
-                    ),
-                ),
-            );
+            .map_err(map_gcp_error);
+        match found {
+            Ok(secret) => Ok(Some(secret)),
+            Err(e) if error_kind(&e) == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to look up secret: {}", name)),
+        }
+    }
 
-            let mut secret = google_cloud_secretmanager_v1::model::Secret::default();
-            secret.replication = Some(replication);
+    /// Create an empty secret with the configured replication and encryption
+    async fn create_secret(
+        &self,
+        environment: &str,
+        name: &str,
+        labels: &BTreeMap<String, String>,
+        annotations: &BTreeMap<String, String>,
+        expire_time: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        let secret_id = self.config.build_secret_name(environment, name);
+        let parent = format!("projects/{}", self.config.gcp_project_id);
 
-            // Create the secret
-            self.client
-                .create_secret()
-                .set_parent(&parent)
-                .set_secret_id(&secret_id)
-                .set_secret(secret)
-                .send()
-                .await
-                .map_err(|e| map_gcp_error(e.into()))
-                .with_context(|| format!("Failed to create secret: {}", name))?;
+        let kms_key_name = self.config.kms_key_name.as_deref();
+        if let Some(key) = kms_key_name {
+            validate_kms_key_name(key)?;
         }
 
+        let locations = self.config.replication_locations.as_deref();
+        let replication = build_replication(locations.unwrap_or_default(), kms_key_name);
+
+        let mut secret = Secret::default();
+        secret.replication = Some(replication);
+        secret.labels = labels.clone().into_iter().collect();
+        secret.annotations = annotations.clone().into_iter().collect();
+        if let Some(time) = expire_time {
+            secret = secret.set_expire_time(Timestamp::clamp(time.timestamp(), 0));
+        }
+
+        self.client
+            .create_secret()
+            .set_parent(&parent)
+            .set_secret_id(&secret_id)
+            .set_secret(secret)
+            .send()
+            .with_timeout(self.timeout)
+            .await
+            .map_err(map_gcp_error)
+            .with_context(|| format!("Failed to create secret: {}", name))?;
+
+        Ok(())
+    }
+
+    /// Add a new version to an existing secret, returning the new version id
+    pub async fn add_secret_version(
+        &self,
+        environment: &str,
+        name: &str,
+        value: &[u8],
+    ) -> Result<String> {
+        validate_secret_size(name, value)?;
+
+        let secret_name = self.config.build_resource_name(environment, name);
+
         let mut payload = google_cloud_secretmanager_v1::model::SecretPayload::default();
-        payload.data = value.as_bytes().to_vec().into();
+        payload.data = value.to_vec().into();
 
         // Add a new version with the secret data
-        self.client
+        let version = self
+            .client
             .add_secret_version()
             .set_parent(&secret_name)
             .set_payload(payload)
             .send()
+            .with_timeout(self.timeout)
             .await
-            .map_err(|e| map_gcp_error(e.into()))
+            .map_err(map_gcp_error)
             .with_context(|| format!("Failed to add secret version: {}", name))?;
 
+        let version_id = version.name.rsplit('/').next().unwrap_or_default();
+        Ok(version_id.to_string())
+    }
+
+    /// Grant `role` on a secret to `member`.
+    ///
+    /// Returns `false` if the member already had the role.
+    pub async fn add_iam_binding(
+        &self,
+        environment: &str,
+        name: &str,
+        member: &str,
+        role: &str,
+    ) -> Result<bool> {
+        self.update_iam_policy(environment, name, |policy| {
+            let binding = policy
+                .bindings
+                .iter_mut()
+                .find(|b| b.role == role && b.condition.is_none());
+
+            match binding {
+                Some(b) if b.members.iter().any(|m| m == member) => false,
+                Some(b) => {
+                    b.members.push(member.to_string());
+                    true
+                }
+                None => {
+                    policy
+                        .bindings
+                        .push(Binding::new().set_role(role).set_members([member]));
+                    true
+                }
+            }
+        })
+        .await
+    }
+
+    /// Remove `member` from `role` on a secret.
+    ///
+    /// Returns `false` if the member didn't have the role.
+    pub async fn remove_iam_binding(
+        &self,
+        environment: &str,
+        name: &str,
+        member: &str,
+        role: &str,
+    ) -> Result<bool> {
+        self.update_iam_policy(environment, name, |policy| {
+            let mut removed = false;
+            for binding in policy
+                .bindings
+                .iter_mut()
+                .filter(|b| b.role == role && b.condition.is_none())
+            {
+                let before = binding.members.len();
+                binding.members.retain(|m| m != member);
+                removed |= binding.members.len() != before;
+            }
+            policy.bindings.retain(|b| !b.members.is_empty());
+            removed
+        })
+        .await
+    }
+
+    /// Read-modify-write the IAM policy of a secret.
+    ///
+    /// `modify` returns whether it changed the policy; unchanged policies are
+    /// not written back. The write carries the etag that was read, so a
+    /// concurrent update makes it fail and the whole cycle is retried.
+    async fn update_iam_policy(
+        &self,
+        environment: &str,
+        name: &str,
+        modify: impl Fn(&mut Policy) -> bool,
+    ) -> Result<bool> {
+        let resource = self.config.build_resource_name(environment, name);
+
+        let mut attempt = 1;
+        loop {
+            let mut policy = self
+                .client
+                .get_iam_policy()
+                .set_resource(&resource)
+                .send()
+                .with_timeout(self.timeout)
+                .await
+                .map_err(map_gcp_error)
+                .with_context(|| format!("Failed to get IAM policy of secret: {}", name))?;
+
+            if !modify(&mut policy) {
+                return Ok(false);
+            }
+
+            let result = self
+                .client
+                .set_iam_policy()
+                .set_resource(&resource)
+                .set_policy(policy)
+                .send()
+                .with_timeout(self.timeout)
+                .await
+                .map_err(map_gcp_error);
+
+            match result {
+                Ok(_) => return Ok(true),
+                Err(e) if is_conflict(&e) && attempt < MAX_IAM_RETRIES => attempt += 1,
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to set IAM policy of secret: {}", name))
+                }
+            }
+        }
+    }
+
+    /// Permanently destroy a single version of a secret
+    pub async fn destroy_secret_version(
+        &self,
+        environment: &str,
+        name: &str,
+        version: &str,
+    ) -> Result<()> {
+        let version_name = self.config.build_version_name(environment, name, version);
+
+        self.client
+            .destroy_secret_version()
+            .set_name(&version_name)
+            .send()
+            .with_timeout(self.timeout)
+            .await
+            .map_err(map_gcp_error)
+            .with_context(|| {
+                format!("Failed to destroy version {} of secret: {}", version, name)
+            })?;
+
         Ok(())
     }
 
@@ -168,10 +757,244 @@ impl SecretManagerClient {
             .delete_secret()
             .set_name(&secret_name)
             .send()
+            .with_timeout(self.timeout)
             .await
-            .map_err(|e| map_gcp_error(e.into()))
+            .map_err(map_gcp_error)
             .with_context(|| format!("Failed to delete secret: {}", name))?;
 
         Ok(())
     }
 }
+
+/// Extract the secret id from its full resource path
+fn short_name(full_name: &str) -> &str {
+    full_name.rsplit('/').next().unwrap_or(full_name)
+}
+
+/// The secrets of one environment under the configured prefix
+fn environment_secrets(config: &Config, secrets: &[Secret], environment: &str) -> Vec<SecretInfo> {
+    let prefix = format!("{}-{}-", config.secret_prefix, environment);
+
+    secrets
+        .iter()
+        .filter_map(|secret| {
+            let secret_name = short_name(&secret.name).strip_prefix(&prefix)?;
+            Some(secret_info(secret, environment, secret_name))
+        })
+        .collect()
+}
+
+/// Build the [`SecretInfo`] for a listed secret
+fn secret_info(secret: &Secret, environment: &str, name: &str) -> SecretInfo {
+    SecretInfo {
+        name: name.to_string(),
+        environment: environment.to_string(),
+        created_at: secret
+            .create_time
+            .as_ref()
+            .map(|t| format_timestamp(t.seconds(), t.nanos())),
+        created_timestamp: secret.create_time.as_ref().map(|t| t.seconds()),
+        expire_time: secret
+            .expire_time()
+            .map(|t| format_timestamp(t.seconds(), t.nanos())),
+        labels: secret.labels.clone().into_iter().collect(),
+        annotations: secret.annotations.clone().into_iter().collect(),
+    }
+}
+
+/// Format a protobuf timestamp for display
+fn format_timestamp(seconds: i64, nanos: i32) -> String {
+    match DateTime::<Utc>::from_timestamp(seconds, nanos as u32) {
+        Some(d) => d.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+        None => "Unknown".to_string(),
+    }
+}
+
+/// Build the replication policy for a new secret.
+///
+/// Uses user-managed replication pinned to `locations` when any are given,
+/// otherwise falls back to automatic replication. When `kms_key_name` is set,
+/// payloads are encrypted with that key (on every replica for user-managed
+/// replication).
+fn build_replication(locations: &[String], kms_key_name: Option<&str>) -> Replication {
+    let encryption = kms_key_name.map(|key| CustomerManagedEncryption::new().set_kms_key_name(key));
+
+    if locations.is_empty() {
+        let mut automatic = replication::Automatic::default();
+        if let Some(encryption) = encryption {
+            automatic = automatic.set_customer_managed_encryption(encryption);
+        }
+        return Replication::new().set_automatic(automatic);
+    }
+
+    let replicas = locations.iter().map(|location| {
+        let replica = replication::user_managed::Replica::new().set_location(location);
+        match &encryption {
+            Some(encryption) => replica.set_customer_managed_encryption(encryption.clone()),
+            None => replica,
+        }
+    });
+
+    Replication::new().set_user_managed(replication::UserManaged::new().set_replicas(replicas))
+}
+
+/// Check that a secret value fits in a single Secret Manager payload
+pub fn validate_secret_size(name: &str, value: &[u8]) -> Result<()> {
+    if value.len() > MAX_SECRET_SIZE {
+        anyhow::bail!(
+            "Value of secret '{}' is {} bytes, but Secret Manager allows at most {} bytes",
+            name,
+            value.len(),
+            MAX_SECRET_SIZE
+        );
+    }
+    Ok(())
+}
+
+/// Check that a KMS key is a full CryptoKey resource name, i.e.
+/// `projects/*/locations/*/keyRings/*/cryptoKeys/*`
+fn validate_kms_key_name(key: &str) -> Result<()> {
+    let parts: Vec<&str> = key.split('/').collect();
+    let valid = parts.len() == 8
+        && parts[0] == "projects"
+        && parts[2] == "locations"
+        && parts[4] == "keyRings"
+        && parts[6] == "cryptoKeys"
+        && parts.iter().all(|p| !p.is_empty());
+
+    if !valid {
+        anyhow::bail!(
+            "Invalid kms_key_name '{}': expected projects/<PROJECT>/locations/<LOCATION>/keyRings/<RING>/cryptoKeys/<KEY>",
+            key
+        );
+    }
+
+    Ok(())
+}
+
+/// Check that an impersonation target looks like a service account email
+pub fn validate_service_account_email(email: &str) -> Result<()> {
+    let valid = match email.split_once('@') {
+        Some((account, domain)) => {
+            !account.is_empty()
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !domain.contains('@')
+                && !email.contains(char::is_whitespace)
+        }
+        None => false,
+    };
+
+    if !valid {
+        anyhow::bail!(
+            "Invalid service account email '{}': expected e.g. deployer@my-project.iam.gserviceaccount.com",
+            email
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KMS_KEY: &str = "projects/p/locations/global/keyRings/r/cryptoKeys/k";
+
+    #[test]
+    fn build_replication_without_locations_is_automatic() {
+        let replication = build_replication(&[], None);
+
+        let automatic = replication.automatic().expect("automatic replication");
+        assert!(automatic.customer_managed_encryption.is_none());
+        assert!(replication.user_managed().is_none());
+    }
+
+    #[test]
+    fn build_replication_automatic_uses_kms_key() {
+        let replication = build_replication(&[], Some(KMS_KEY));
+
+        let automatic = replication.automatic().expect("automatic replication");
+        let encryption = automatic.customer_managed_encryption.as_ref().unwrap();
+        assert_eq!(encryption.kms_key_name, KMS_KEY);
+    }
+
+    #[test]
+    fn build_replication_with_locations_is_user_managed() {
+        let locations = vec!["europe-west1".to_string(), "us-east1".to_string()];
+        let replication = build_replication(&locations, None);
+
+        assert!(replication.automatic().is_none());
+        let user_managed = replication
+            .user_managed()
+            .expect("user-managed replication");
+        let replicas: Vec<_> = user_managed
+            .replicas
+            .iter()
+            .map(|r| r.location.as_str())
+            .collect();
+        assert_eq!(replicas, ["europe-west1", "us-east1"]);
+        assert!(user_managed
+            .replicas
+            .iter()
+            .all(|r| r.customer_managed_encryption.is_none()));
+    }
+
+    #[test]
+    fn build_replication_user_managed_uses_kms_key_per_replica() {
+        let locations = vec!["europe-west1".to_string(), "us-east1".to_string()];
+        let replication = build_replication(&locations, Some(KMS_KEY));
+
+        let user_managed = replication
+            .user_managed()
+            .expect("user-managed replication");
+        assert_eq!(user_managed.replicas.len(), 2);
+        for replica in &user_managed.replicas {
+            let encryption = replica.customer_managed_encryption.as_ref().unwrap();
+            assert_eq!(encryption.kms_key_name, KMS_KEY);
+        }
+    }
+
+    #[test]
+    fn environment_secrets_filters_by_overridden_prefix() {
+        let config = Config {
+            gcp_project_id: "p".to_string(),
+            secret_prefix: "ci".to_string(),
+            ..Default::default()
+        };
+        let secrets: Vec<Secret> = [
+            "ci-dev-db",
+            "ci-dev-api-key",
+            "k8s-dev-db",
+            "ci-prod-db",
+            "ci-development-db",
+        ]
+        .iter()
+        .map(|id| Secret::new().set_name(format!("projects/p/secrets/{}", id)))
+        .collect();
+
+        let names: Vec<_> = environment_secrets(&config, &secrets, "dev")
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, ["db", "api-key"]);
+    }
+
+    #[test]
+    fn validate_secret_size_rejects_oversized_value() {
+        let value = vec![b'x'; 70 * 1024];
+
+        let err = validate_secret_size("big-cert", &value).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Value of secret 'big-cert' is 71680 bytes, but Secret Manager allows at most 65536 bytes"
+        );
+    }
+
+    #[test]
+    fn validate_secret_size_accepts_value_at_limit() {
+        assert!(validate_secret_size("cert", &vec![b'x'; MAX_SECRET_SIZE]).is_ok());
+    }
+}
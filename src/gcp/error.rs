@@ -1,34 +1,136 @@
 use anyhow::{anyhow, Error};
-use tonic::Code;
+
+use crate::error::{ApiError, ErrorKind};
+
+/// Guidance shown whenever GCP rejects or can't find credentials
+const LOGIN_HINT: &str = "Authentication failed.\n\
+    Run 'gcloud auth application-default login' to authenticate your local environment.";
 
 pub fn map_gcp_error(err: Error) -> Error {
-    // Try to downcast to tonic::Status
-    if let Some(status) = err.downcast_ref::<tonic::Status>() {
-        return match status.code() {
-            Code::Unauthenticated => anyhow!(
-                "Authentication failed.\n\
-                 Run 'gcloud auth application-default login' to authenticate your local environment."
-            ),
-            Code::PermissionDenied => anyhow!(
-                "Permission denied.\n\
-                 Ensure your account has the 'Secret Manager Secret Accessor' (roles/secretmanager.secretAccessor) role for this project."
-            ),
-            Code::NotFound => anyhow!(
-                "Resource not found.\n\
-                 Check if the GCP project ID is correct and the secret exists."
-            ),
-            Code::AlreadyExists => anyhow!(
-                "Resource already exists.\n\
-                 You are trying to create a secret that is already present."
-            ),
-            Code::Unavailable => anyhow!(
-                "Service unavailable.\n\
-                 Google Cloud Secret Manager might be experiencing issues or you have connectivity problems."
-            ),
-            _ => anyhow!("Google Cloud Error: {}", status.message()),
-        };
+    // The client library reports gRPC status codes on its own error type; a
+    // tonic::Status is handled the same way
+    let (code, message) = if let Some(status) = err.downcast_ref::<tonic::Status>() {
+        (format!("{:?}", status.code()), status.message().to_string())
+    } else if let Some(status) = err
+        .downcast_ref::<google_cloud_secretmanager_v1::Error>()
+        .and_then(|e| e.status())
+    {
+        (format!("{:?}", status.code), status.message.clone())
+    } else {
+        // Not an API status, just return the original error
+        return err;
+    };
+
+    let hint = match code.as_str() {
+        "Unauthenticated" => anyhow!(LOGIN_HINT),
+        "PermissionDenied" if is_impersonation_error(&message) => anyhow!(
+            "Permission denied while impersonating the service account.\n\
+             Ensure your account has the 'Service Account Token Creator' (roles/iam.serviceAccountTokenCreator) role on the impersonated service account."
+        ),
+        "PermissionDenied" if is_kms_error(&message) => anyhow!(
+            "Permission denied on the Cloud KMS key.\n\
+             Grant the Secret Manager service agent (service-<PROJECT_NUMBER>@gcp-sa-secretmanager.iam.gserviceaccount.com) the 'Cloud KMS CryptoKey Encrypter/Decrypter' (roles/cloudkms.cryptoKeyEncrypterDecrypter) role on the configured kms_key_name."
+        ),
+        "PermissionDenied" => anyhow!(
+            "Permission denied.\n\
+             Ensure your account has the 'Secret Manager Secret Accessor' (roles/secretmanager.secretAccessor) role for this project."
+        ),
+        "NotFound" => anyhow!(
+            "Resource not found.\n\
+             Check if the GCP project ID is correct and the secret exists."
+        ),
+        "AlreadyExists" => anyhow!(
+            "Resource already exists.\n\
+             You are trying to create a secret that is already present."
+        ),
+        "Unavailable" => anyhow!(
+            "Service unavailable.\n\
+             Google Cloud Secret Manager might be experiencing issues or you have connectivity problems."
+        ),
+        _ => anyhow!("Google Cloud Error: {}", message),
+    };
+
+    ApiError {
+        service: "gcp",
+        kind: ErrorKind::from_gcp_code(&code),
+        code,
+        message: hint.to_string(),
+    }
+    .into()
+}
+
+/// Map a failure to load credentials or mint a token to the same
+/// unauthenticated error (and login guidance) as an API rejection
+pub fn map_auth_error(err: impl std::fmt::Display) -> Error {
+    tracing::debug!(error = %err, "failed to obtain GCP credentials");
+
+    ApiError {
+        service: "gcp",
+        code: "Unauthenticated".to_string(),
+        kind: ErrorKind::Unauthenticated,
+        message: LOGIN_HINT.to_string(),
     }
+    .into()
+}
 
-    // If it's not a tonic::Status, just return the original error
-    err
+/// Whether a mapped error is a concurrent-modification conflict (e.g. a stale
+/// IAM policy etag). `FailedPrecondition` isn't one: it reports a permanent
+/// problem such as a disabled secret, which retrying won't fix.
+pub fn is_conflict(err: &Error) -> bool {
+    matches!(
+        err.downcast_ref::<ApiError>(),
+        Some(e) if e.service == "gcp" && e.code == "Aborted"
+    )
+}
+
+/// Whether a mapped error means the resource being created already exists
+pub fn is_already_exists(err: &Error) -> bool {
+    matches!(
+        err.downcast_ref::<ApiError>(),
+        Some(e) if e.service == "gcp" && e.code == "AlreadyExists"
+    )
+}
+
+/// Whether a mapped error means the resource is in the wrong state, e.g. a
+/// disabled or destroyed secret version was accessed
+pub fn is_failed_precondition(err: &Error) -> bool {
+    matches!(
+        err.downcast_ref::<ApiError>(),
+        Some(e) if e.service == "gcp" && e.code == "FailedPrecondition"
+    )
+}
+
+/// Whether a permission error was caused by the customer-managed encryption key
+fn is_kms_error(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    message.contains("cloudkms") || message.contains("cryptokey")
+}
+
+/// Whether a permission error came from minting a token for an impersonated
+/// service account
+fn is_impersonation_error(message: &str) -> bool {
+    message.contains("iam.serviceAccounts.getAccessToken")
+        || message.contains("iamcredentials.googleapis.com")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gcp_error(code: &str) -> Error {
+        ApiError {
+            service: "gcp",
+            code: code.to_string(),
+            kind: ErrorKind::Other,
+            message: "request failed".to_string(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn only_aborted_is_a_conflict() {
+        assert!(is_conflict(&gcp_error("Aborted")));
+        assert!(!is_conflict(&gcp_error("FailedPrecondition")));
+        assert!(!is_conflict(&gcp_error("InvalidArgument")));
+    }
 }
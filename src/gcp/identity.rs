@@ -0,0 +1,99 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use google_cloud_auth::credentials::{self, impersonated};
+use serde::Deserialize;
+
+use crate::config::Config;
+use crate::gcp::client::validate_service_account_email;
+use crate::gcp::error::map_auth_error;
+use crate::timeout::TimeoutExt;
+
+/// Google's endpoint describing an OAuth access token
+const TOKEN_INFO_URL: &str = "https://oauth2.googleapis.com/tokeninfo";
+/// Scopes requested for the token; the email scope lets token info name the
+/// principal
+const SCOPES: [&str; 2] = [
+    "https://www.googleapis.com/auth/cloud-platform",
+    "https://www.googleapis.com/auth/userinfo.email",
+];
+
+/// Fields of a token info response (or self-signed JWT) naming the principal
+#[derive(Debug, Deserialize)]
+struct TokenInfo {
+    email: Option<String>,
+    /// Issuer of a self-signed service account JWT
+    iss: Option<String>,
+}
+
+/// Mint an access token the same way the Secret Manager client authenticates,
+/// including any configured impersonation
+pub async fn access_token(config: &Config) -> Result<String> {
+    let credentials = match config.impersonate_service_account.as_deref() {
+        Some(email) => {
+            validate_service_account_email(email)?;
+
+            let source = credentials::Builder::default()
+                .build()
+                .map_err(map_auth_error)?;
+            impersonated::Builder::from_source_credentials(source)
+                .with_target_principal(email)
+                .with_scopes(SCOPES)
+                .build_access_token_credentials()
+        }
+        None => credentials::Builder::default()
+            .with_scopes(SCOPES)
+            .build_access_token_credentials(),
+    }
+    .map_err(map_auth_error)?;
+
+    let token = credentials
+        .access_token()
+        .with_timeout(config.timeout())
+        .await
+        .map_err(map_auth_error)?;
+
+    Ok(token.token)
+}
+
+/// Resolve the principal (user or service account email) a token belongs to.
+///
+/// Service account keys yield self-signed JWTs, which name their account
+/// directly; other tokens are looked up with Google's token info endpoint.
+pub async fn principal(config: &Config, token: &str) -> Result<Option<String>> {
+    if let Some(info) = jwt_claims(token) {
+        return Ok(info.email.or(info.iss));
+    }
+
+    let response = reqwest::Client::new()
+        .get(TOKEN_INFO_URL)
+        .query(&[("access_token", token)])
+        .send()
+        .with_timeout(config.timeout())
+        .await
+        .context("Failed to look up the access token")?;
+
+    if !response.status().is_success() {
+        return Err(map_auth_error(format!(
+            "token info returned {}",
+            response.status()
+        )));
+    }
+
+    let info: TokenInfo = response
+        .json()
+        .await
+        .context("Failed to parse the token info response")?;
+
+    Ok(info.email)
+}
+
+/// Claims of a self-signed JWT, or `None` if the token isn't one
+fn jwt_claims(token: &str) -> Option<TokenInfo> {
+    let mut parts = token.split('.');
+    let (_, payload, _) = (parts.next()?, parts.next()?, parts.next()?);
+
+    let decoded = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload)
+        .ok()?;
+    serde_json::from_slice(&decoded).ok()
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// Maximum number of labels GCP allows on a secret
+const MAX_LABELS: usize = 64;
+/// Maximum length of a label key or value
+const MAX_LABEL_LENGTH: usize = 63;
+
+/// Parse `key=value` label arguments into a validated map
+pub fn parse_labels(args: &[String]) -> Result<BTreeMap<String, String>> {
+    let mut labels = BTreeMap::new();
+
+    for arg in args {
+        let (key, value) = arg
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Invalid label '{}': expected key=value", arg))?;
+        validate_label(key, value)?;
+        labels.insert(key.to_string(), value.to_string());
+    }
+
+    if labels.len() > MAX_LABELS {
+        anyhow::bail!(
+            "Too many labels: GCP allows at most {} per secret",
+            MAX_LABELS
+        );
+    }
+
+    Ok(labels)
+}
+
+/// Validate a label against GCP's constraints.
+///
+/// Keys must start with a lowercase letter; keys and values may only contain
+/// lowercase letters, digits, underscores and dashes, up to 63 characters.
+pub fn validate_label(key: &str, value: &str) -> Result<()> {
+    let is_allowed = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-';
+
+    if !key.starts_with(|c: char| c.is_ascii_lowercase()) {
+        anyhow::bail!(
+            "Invalid label key '{}': must start with a lowercase letter",
+            key
+        );
+    }
+    if key.len() > MAX_LABEL_LENGTH || !key.chars().all(is_allowed) {
+        anyhow::bail!(
+            "Invalid label key '{}': use up to {} lowercase letters, digits, '_' or '-'",
+            key,
+            MAX_LABEL_LENGTH
+        );
+    }
+    if value.len() > MAX_LABEL_LENGTH || !value.chars().all(is_allowed) {
+        anyhow::bail!(
+            "Invalid label value '{}' for key '{}': use up to {} lowercase letters, digits, '_' or '-'",
+            value,
+            key,
+            MAX_LABEL_LENGTH
+        );
+    }
+
+    Ok(())
+}
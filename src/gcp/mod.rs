@@ -1,4 +1,7 @@
+pub mod annotations;
 pub mod client;
 pub mod error;
+pub mod identity;
+pub mod labels;
 
 pub use client::SecretManagerClient;
@@ -1,57 +1,281 @@
 use crate::k8s::error::map_k8s_error;
+use crate::timeout::TimeoutExt;
 use anyhow::{Context, Result};
 use k8s_openapi::api::core::v1::Secret;
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
 use k8s_openapi::ByteString;
 use kube::{
-    api::{Api, DeleteParams, PostParams},
+    api::{Api, DeleteParams, Patch, PatchParams, PostParams},
     config::{KubeConfigOptions, Kubeconfig},
     Client, Config,
 };
+use serde::Serialize;
 use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Default server-side apply field manager
+pub const DEFAULT_FIELD_MANAGER: &str = "ksecret";
+/// Maximum length Kubernetes allows for a field manager name
+const MAX_FIELD_MANAGER_LEN: usize = 128;
+/// How often to check whether a deleted secret is gone before recreating it
+const DELETE_POLL_ATTEMPTS: u32 = 6;
+/// First delay between deletion checks; doubled after every check
+const DELETE_POLL_INITIAL_DELAY: Duration = Duration::from_millis(25);
+
+/// How `apply_secret` writes a secret
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ApplyStrategy {
+    /// Server-side apply, updating the secret in place
+    #[default]
+    Patch,
+    /// Delete the secret and create it again, for clusters whose admission
+    /// controllers reject in-place updates. The secret is briefly missing.
+    Recreate,
+}
+
+impl std::str::FromStr for ApplyStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "patch" => Ok(Self::Patch),
+            "recreate" => Ok(Self::Recreate),
+            _ => anyhow::bail!(
+                "Unknown apply strategy '{}' (expected patch or recreate)",
+                s
+            ),
+        }
+    }
+}
+
+/// Timeouts of a [`KubeClient`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KubeTimeouts {
+    /// Bound on each whole API call (`--timeout`)
+    pub operation: Option<Duration>,
+    /// Bound on connecting to the API server
+    pub connect: Option<Duration>,
+    /// Bound on reading / writing a request's data
+    pub request: Option<Duration>,
+}
+
+impl KubeTimeouts {
+    /// Timeouts configured in the ksecret config
+    pub fn from_config(config: &crate::config::Config) -> Self {
+        Self {
+            operation: config.timeout(),
+            connect: config.k8s_connect_timeout(),
+            request: config.k8s_request_timeout(),
+        }
+    }
+}
+
+/// A context defined in the kubeconfig
+#[derive(Debug, Clone, Serialize)]
+pub struct KubeContext {
+    pub name: String,
+    pub cluster: Option<String>,
+    pub namespace: Option<String>,
+    /// Whether this is the kubeconfig's current context
+    pub current: bool,
+}
 
 /// Wrapper around Kubernetes client for secret operations
 pub struct KubeClient {
     client: Client,
+    timeout: Option<Duration>,
+    field_manager: String,
+    apply_strategy: ApplyStrategy,
+    /// Mark applied secrets immutable
+    immutable: bool,
+    /// Namespace set on the selected context (or the pod's namespace
+    /// in-cluster)
+    default_namespace: Option<String>,
 }
 
 impl KubeClient {
-    /// Create a new Kubernetes client using the specified context or default
-    pub async fn new(context: Option<&str>) -> Result<Self> {
-        let config = if let Some(ctx) = context {
+    /// Create a new Kubernetes client using the specified context or default.
+    ///
+    /// With `in_cluster`, the pod's service account is used and any kubeconfig
+    /// is ignored. `timeouts.operation` bounds each individual API call; the
+    /// connect and request timeouts replace kube's defaults when set.
+    pub async fn new(
+        context: Option<&str>,
+        in_cluster: bool,
+        timeouts: KubeTimeouts,
+    ) -> Result<Self> {
+        if in_cluster && context.is_some() {
+            anyhow::bail!("--context and --in-cluster cannot be used together");
+        }
+
+        let (config, default_namespace) = if in_cluster {
+            let config = Config::incluster().context(
+                "Failed to load in-cluster config; --in-cluster only works when running inside a Kubernetes pod",
+            )?;
+            let namespace = config.default_namespace.clone();
+            (config, Some(namespace))
+        } else if let Some(ctx) = context {
             // Load kubeconfig with specific context
             let kubeconfig = Kubeconfig::read()
                 .map_err(|e| map_k8s_error(e.into()))
                 .context("Failed to read kubeconfig")?;
+            let namespace = context_namespace(&kubeconfig, Some(ctx));
             let options = KubeConfigOptions {
                 context: Some(ctx.to_string()),
                 ..Default::default()
             };
-            Config::from_custom_kubeconfig(kubeconfig, &options)
+            let config = Config::from_custom_kubeconfig(kubeconfig, &options)
                 .await
                 .map_err(|e| map_k8s_error(e.into()))
-                .with_context(|| format!("Failed to create config for context: {}", ctx))?
+                .with_context(|| format!("Failed to create config for context: {}", ctx))?;
+            (config, namespace)
         } else {
             // Use default config (in-cluster or default context)
-            Config::infer()
+            let config = Config::infer()
                 .await
                 .map_err(|e| map_k8s_error(e.into()))
-                .context("Failed to infer Kubernetes config")?
+                .context("Failed to infer Kubernetes config")?;
+            let namespace = match Kubeconfig::read() {
+                Ok(kubeconfig) => context_namespace(&kubeconfig, None),
+                // Inferred from the in-cluster service account
+                Err(_) => Some(config.default_namespace.clone()),
+            };
+            (config, namespace)
         };
 
+        Self::from_config(config, timeouts, default_namespace)
+    }
+
+    /// Create a client for an already loaded kube config
+    fn from_config(
+        mut config: Config,
+        timeouts: KubeTimeouts,
+        default_namespace: Option<String>,
+    ) -> Result<Self> {
+        if let Some(connect) = timeouts.connect {
+            config.connect_timeout = Some(connect);
+        }
+        if let Some(request) = timeouts.request {
+            config.read_timeout = Some(request);
+            config.write_timeout = Some(request);
+        }
+
         let client = Client::try_from(config)
             .map_err(|e| map_k8s_error(e.into()))
             .context("Failed to create Kubernetes client")?;
 
-        Ok(Self { client })
+        Ok(Self {
+            client,
+            timeout: timeouts.operation,
+            field_manager: DEFAULT_FIELD_MANAGER.to_string(),
+            apply_strategy: ApplyStrategy::default(),
+            immutable: false,
+            default_namespace,
+        })
     }
 
-    /// Create or update a secret in the specified namespace
+    /// Namespace configured on the selected kubeconfig context, or the pod's
+    /// namespace when running in-cluster. `None` if the context sets none.
+    pub fn default_namespace(&self) -> Option<&str> {
+        self.default_namespace.as_deref()
+    }
+
+    /// Write secrets with `strategy` in `apply_secret`
+    pub fn with_apply_strategy(mut self, strategy: ApplyStrategy) -> Self {
+        self.apply_strategy = strategy;
+        self
+    }
+
+    /// How `apply_secret` writes secrets
+    pub fn apply_strategy(&self) -> ApplyStrategy {
+        self.apply_strategy
+    }
+
+    /// Mark secrets written by `apply_secret` as immutable. Since their data
+    /// can't be updated in place, changed secrets are re-created.
+    pub fn with_immutable(mut self, immutable: bool) -> Self {
+        self.immutable = immutable;
+        self
+    }
+
+    /// Use `name` as the server-side apply field manager for applied secrets
+    pub fn with_field_manager(mut self, name: &str) -> Result<Self> {
+        if name.is_empty() {
+            anyhow::bail!("Field manager must not be empty");
+        }
+        if name.len() > MAX_FIELD_MANAGER_LEN {
+            anyhow::bail!(
+                "Field manager '{}' is longer than {} characters",
+                name,
+                MAX_FIELD_MANAGER_LEN
+            );
+        }
+
+        self.field_manager = name.to_string();
+        Ok(self)
+    }
+
+    /// Name of the kubeconfig context that would be used, or "in-cluster" when
+    /// using the pod's service account
+    pub fn context_name(context: Option<&str>, in_cluster: bool) -> Result<String> {
+        if in_cluster {
+            return Ok("in-cluster".to_string());
+        }
+        if let Some(ctx) = context {
+            return Ok(ctx.to_string());
+        }
+
+        match Kubeconfig::read() {
+            Ok(kubeconfig) => kubeconfig
+                .current_context
+                .context("Kubeconfig has no current context"),
+            Err(_) => Ok("in-cluster".to_string()),
+        }
+    }
+
+    /// Every context in the kubeconfig, in the order they're defined
+    pub fn contexts() -> Result<Vec<KubeContext>> {
+        let kubeconfig = Kubeconfig::read()
+            .map_err(|e| map_k8s_error(e.into()))
+            .context("Failed to read kubeconfig")?;
+        let current = kubeconfig.current_context.as_deref();
+
+        Ok(kubeconfig
+            .contexts
+            .iter()
+            .map(|c| {
+                let context = c.context.as_ref();
+                KubeContext {
+                    name: c.name.clone(),
+                    cluster: context.map(|ctx| ctx.cluster.clone()),
+                    namespace: context.and_then(|ctx| ctx.namespace.clone()),
+                    current: current == Some(c.name.as_str()),
+                }
+            })
+            .collect())
+    }
+
+    /// Create or update a secret in the specified namespace.
+    ///
+    /// `labels` and `annotations` are added next to our managed-by label. With
+    /// the patch strategy, labels and annotations set by others are left alone,
+    /// since server-side apply only owns the fields we send; recreating the
+    /// secret drops them.
+    ///
+    /// The span records where the secret goes, never its data.
+    #[tracing::instrument(
+        name = "k8s.apply_secret",
+        skip_all,
+        fields(namespace = %namespace, name = %name, secret_type = %secret_type)
+    )]
     pub async fn apply_secret(
         &self,
         namespace: &str,
         name: &str,
         data: BTreeMap<String, Vec<u8>>,
+        secret_type: &str,
+        labels: &BTreeMap<String, String>,
+        annotations: &BTreeMap<String, String>,
     ) -> Result<()> {
         let secrets: Api<Secret> = Api::namespaced(self.client.clone(), namespace);
 
@@ -59,73 +283,225 @@ impl KubeClient {
         let secret_data: BTreeMap<String, ByteString> =
             data.into_iter().map(|(k, v)| (k, ByteString(v))).collect();
 
+        let mut labels = labels.clone();
+        labels.insert(
+            "app.kubernetes.io/managed-by".to_string(),
+            "ksecret".to_string(),
+        );
+
         let secret = Secret {
             metadata: ObjectMeta {
                 name: Some(name.to_string()),
                 namespace: Some(namespace.to_string()),
-                labels: Some(BTreeMap::from([(
-                    "app.kubernetes.io/managed-by".to_string(),
-                    "ksecret".to_string(),
-                )])),
+                labels: Some(labels),
+                annotations: (!annotations.is_empty()).then(|| annotations.clone()),
                 ..Default::default()
             },
             data: Some(secret_data),
-            type_: Some("Opaque".to_string()),
+            type_: Some(secret_type.to_string()),
+            immutable: self.immutable.then_some(true),
             ..Default::default()
         };
 
-        // Delete existing secret if it exists (delete-then-recreate strategy)
-        let delete_params = DeleteParams::default();
-        match secrets.delete(name, &delete_params).await {
-            Ok(_) => {
-                // Wait for deletion to verify it's gone?
-                // k8s delete is usually async, but for secrets it's often fast.
-                // We'll proceed to create. If we get a conflict, we might need to retry,
-                // but usually the UID changes so it's fine.
+        tracing::debug!(
+            namespace,
+            name,
+            field_manager = %self.field_manager,
+            strategy = ?self.apply_strategy,
+            immutable = self.immutable,
+            "Applying secret"
+        );
+
+        match self.apply_strategy {
+            // Server-side apply keeps the resource UID stable and avoids a
+            // window where the secret doesn't exist
+            ApplyStrategy::Patch => {
+                let patch_params = PatchParams::apply(&self.field_manager).force();
+                let patched = secrets
+                    .patch(name, &patch_params, &Patch::Apply(&secret))
+                    .with_timeout(self.timeout)
+                    .await;
+                match patched {
+                    Ok(_) => {}
+                    // API servers without server-side apply reject the patch
+                    // type; replace the secret there instead
+                    Err(e) if is_status(&e, 415) => {
+                        self.recreate_secret(&secrets, name, &secret).await?
+                    }
+                    // A secret's type can't be changed in place either
+                    Err(e) if is_immutable_type(&e) => {
+                        tracing::info!(
+                            namespace,
+                            name,
+                            to = secret_type,
+                            "Secret type changed, deleting and re-creating it"
+                        );
+                        self.recreate_secret(&secrets, name, &secret).await?;
+                    }
+                    // An immutable secret's data can only change by replacing it
+                    Err(e) if self.immutable && is_immutable_field(&e) => {
+                        tracing::info!(
+                            namespace,
+                            name,
+                            "Secret is immutable, deleting and re-creating it to apply the change"
+                        );
+                        self.recreate_secret(&secrets, name, &secret).await?;
+                    }
+                    Err(e) if is_immutable_field(&e) => {
+                        return Err(map_k8s_error(e)).with_context(|| {
+                            format!(
+                                "Secret '{}' is immutable; sync with --immutable or --apply-strategy recreate to replace it",
+                                name
+                            )
+                        })
+                    }
+                    Err(e) => {
+                        return Err(map_k8s_error(e))
+                            .with_context(|| format!("Failed to apply secret: {}", name))
+                    }
+                }
             }
-            Err(kube::Error::Api(e)) if e.code == 404 => {
-                // Secret didn't exist, safe to proceed
+            ApplyStrategy::Recreate => self.recreate_secret(&secrets, name, &secret).await?,
+        }
+
+        Ok(())
+    }
+
+    /// Delete a secret (if present), wait until it's gone and create it again
+    async fn recreate_secret(
+        &self,
+        secrets: &Api<Secret>,
+        name: &str,
+        secret: &Secret,
+    ) -> Result<()> {
+        let deleted = secrets
+            .delete(name, &DeleteParams::default())
+            .with_timeout(self.timeout)
+            .await;
+        match deleted {
+            Ok(_) => self.wait_for_deletion(secrets, name).await?,
+            Err(e) if is_status(&e, 404) => {}
+            Err(e) => {
+                return Err(map_k8s_error(e))
+                    .with_context(|| format!("Failed to delete secret: {}", name))
             }
-            Err(e) => return Err(map_k8s_error(e.into())),
         }
 
-        // Create the secret freshly
-        let post_params = PostParams::default();
-        secrets
-            .create(&post_params, &secret)
+        let post_params = PostParams {
+            field_manager: Some(self.field_manager.clone()),
+            ..Default::default()
+        };
+        match secrets
+            .create(&post_params, secret)
+            .with_timeout(self.timeout)
             .await
-            .map_err(|e| map_k8s_error(e.into()))
-            .with_context(|| format!("Failed to create secret: {}", name))?;
+        {
+            Ok(_) => Ok(()),
+            Err(e) if is_conflict(&e) => anyhow::bail!(
+                "Secret '{}' was re-created by someone else while it was being replaced; run the sync again",
+                name
+            ),
+            Err(e) => Err(map_k8s_error(e))
+                .with_context(|| format!("Failed to create secret: {}", name)),
+        }
+    }
 
-        Ok(())
+    /// Poll with exponential backoff until a deleted secret no longer exists
+    async fn wait_for_deletion(&self, secrets: &Api<Secret>, name: &str) -> Result<()> {
+        let mut delay = DELETE_POLL_INITIAL_DELAY;
+        for attempt in 1..=DELETE_POLL_ATTEMPTS {
+            let found = secrets
+                .get_opt(name)
+                .with_timeout(self.timeout)
+                .await
+                .map_err(map_k8s_error)
+                .with_context(|| format!("Failed to get secret: {}", name))?;
+            if found.is_none() {
+                return Ok(());
+            }
+
+            // Nothing is checked after the last attempt, so don't wait for it
+            if attempt < DELETE_POLL_ATTEMPTS {
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+        }
+
+        anyhow::bail!(
+            "Secret '{}' is still being deleted (it may have finalizers); run the sync again once it's gone",
+            name
+        )
+    }
+
+    /// Get the decoded data of a secret, or `None` if it doesn't exist
+    pub async fn get_secret(
+        &self,
+        namespace: &str,
+        name: &str,
+    ) -> Result<Option<BTreeMap<String, Vec<u8>>>> {
+        Ok(self
+            .get_secret_with_annotations(namespace, name)
+            .await?
+            .map(|(data, _)| data))
+    }
+
+    /// Like [`get_secret`](Self::get_secret), also returning the secret's
+    /// annotations
+    pub async fn get_secret_with_annotations(
+        &self,
+        namespace: &str,
+        name: &str,
+    ) -> Result<Option<(BTreeMap<String, Vec<u8>>, BTreeMap<String, String>)>> {
+        let secrets: Api<Secret> = Api::namespaced(self.client.clone(), namespace);
+
+        let secret = secrets
+            .get_opt(name)
+            .with_timeout(self.timeout)
+            .await
+            .map_err(map_k8s_error)
+            .with_context(|| format!("Failed to get secret: {}", name))?;
+
+        Ok(secret.map(|s| {
+            let data = s
+                .data
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(k, v)| (k, v.0))
+                .collect();
+            (data, s.metadata.annotations.unwrap_or_default())
+        }))
     }
 
     /// Delete a secret from the specified namespace
-    #[allow(dead_code)]
     pub async fn delete_secret(&self, namespace: &str, name: &str) -> Result<()> {
         let secrets: Api<Secret> = Api::namespaced(self.client.clone(), namespace);
 
         secrets
             .delete(name, &Default::default())
+            .with_timeout(self.timeout)
             .await
-            .map_err(|e| map_k8s_error(e.into()))
+            .map_err(map_k8s_error)
             .with_context(|| format!("Failed to delete secret: {}", name))?;
 
         Ok(())
     }
 
-    /// List all secrets in a namespace managed by ksecret
-    #[allow(dead_code)]
-    pub async fn list_managed_secrets(&self, namespace: &str) -> Result<Vec<String>> {
+    /// List the secrets in a namespace managed by ksecret that also carry all
+    /// of `labels`
+    pub async fn list_managed_secrets(
+        &self,
+        namespace: &str,
+        labels: &BTreeMap<String, String>,
+    ) -> Result<Vec<String>> {
         let secrets: Api<Secret> = Api::namespaced(self.client.clone(), namespace);
 
-        let list_params =
-            kube::api::ListParams::default().labels("app.kubernetes.io/managed-by=ksecret");
+        let list_params = kube::api::ListParams::default().labels(&managed_selector(labels));
 
         let secret_list = secrets
             .list(&list_params)
+            .with_timeout(self.timeout)
             .await
-            .map_err(|e| map_k8s_error(e.into()))
+            .map_err(map_k8s_error)
             .context("Failed to list secrets")?;
 
         let names: Vec<String> = secret_list
@@ -142,10 +518,445 @@ impl KubeClient {
         use k8s_openapi::api::core::v1::Namespace;
 
         let namespaces: Api<Namespace> = Api::all(self.client.clone());
-        match namespaces.get(namespace).await {
-            Ok(_) => Ok(true),
-            Err(kube::Error::Api(err)) if err.code == 404 => Ok(false),
-            Err(e) => Err(map_k8s_error(e.into())).context("Failed to check namespace"),
+        let found = namespaces
+            .get_opt(namespace)
+            .with_timeout(self.timeout)
+            .await
+            .map_err(map_k8s_error)
+            .context("Failed to check namespace")?;
+
+        Ok(found.is_some())
+    }
+
+    /// Create a namespace managed by ksecret.
+    ///
+    /// An already existing namespace (409 Conflict) is treated as success so that
+    /// parallel runs don't race each other.
+    pub async fn create_namespace(&self, name: &str) -> Result<()> {
+        use k8s_openapi::api::core::v1::Namespace;
+
+        let namespaces: Api<Namespace> = Api::all(self.client.clone());
+        let namespace = Namespace {
+            metadata: ObjectMeta {
+                name: Some(name.to_string()),
+                labels: Some(BTreeMap::from([(
+                    "app.kubernetes.io/managed-by".to_string(),
+                    "ksecret".to_string(),
+                )])),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let result = namespaces
+            .create(&PostParams::default(), &namespace)
+            .with_timeout(self.timeout)
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if is_conflict(&e) => Ok(()),
+            Err(e) => Err(map_k8s_error(e))
+                .with_context(|| format!("Failed to create namespace: {}", name)),
         }
     }
 }
+
+/// Namespace of a kubeconfig context (the current one when `name` is `None`)
+fn context_namespace(kubeconfig: &Kubeconfig, name: Option<&str>) -> Option<String> {
+    let name = name.or(kubeconfig.current_context.as_deref())?;
+    kubeconfig
+        .contexts
+        .iter()
+        .find(|c| c.name == name)?
+        .context
+        .as_ref()?
+        .namespace
+        .clone()
+}
+
+/// Label selector for ksecret-managed resources carrying all of `labels`
+fn managed_selector(labels: &BTreeMap<String, String>) -> String {
+    std::iter::once("app.kubernetes.io/managed-by=ksecret".to_string())
+        .chain(
+            labels
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value)),
+        )
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Whether an error is a Kubernetes 409 Conflict (e.g. AlreadyExists)
+fn is_conflict(err: &anyhow::Error) -> bool {
+    is_status(err, 409)
+}
+
+/// Whether an error is the API server refusing to change a field of an
+/// immutable resource (422 Invalid, "field is immutable")
+fn is_immutable_field(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<kube::Error>(),
+        Some(kube::Error::Api(e)) if e.code == 422 && e.message.contains("field is immutable")
+    )
+}
+
+/// Whether an error is the API server refusing to change a secret's type
+/// (422 Invalid, "type: Invalid value: ...: field is immutable")
+fn is_immutable_type(err: &anyhow::Error) -> bool {
+    is_immutable_field(err)
+        && matches!(
+            err.downcast_ref::<kube::Error>(),
+            Some(kube::Error::Api(e)) if e.message.contains("type: Invalid value")
+        )
+}
+
+/// Whether an error is a Kubernetes API error with the given HTTP status
+fn is_status(err: &anyhow::Error, code: u16) -> bool {
+    matches!(err.downcast_ref::<kube::Error>(), Some(kube::Error::Api(e)) if e.code == code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::k8s::error::KubeTimeoutError;
+    use http::{Method, Request, Response, StatusCode};
+    use kube::client::Body;
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// A request a mock API server received
+    struct Received {
+        /// "METHOD path"
+        line: String,
+        content_type: Option<String>,
+        body: Option<Value>,
+    }
+
+    type RequestLog = Arc<Mutex<Vec<Received>>>;
+
+    /// The "METHOD path" lines of the requests received so far
+    fn lines(log: &RequestLog) -> Vec<String> {
+        log.lock().unwrap().iter().map(|r| r.line.clone()).collect()
+    }
+
+    /// A client whose API calls are answered by `respond` instead of a cluster
+    fn mock_client(
+        respond: impl Fn(&Method, &str) -> (StatusCode, Value) + Send + Sync + 'static,
+    ) -> (KubeClient, RequestLog) {
+        let log = RequestLog::default();
+        let seen = log.clone();
+        let service = tower::service_fn(move |request: Request<Body>| {
+            let (method, path) = (request.method().clone(), request.uri().path().to_string());
+            let (status, response) = respond(&method, &path);
+            let content_type = request
+                .headers()
+                .get(http::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string);
+            let seen = seen.clone();
+            async move {
+                let body = request.into_body().collect_bytes().await.unwrap();
+                seen.lock().unwrap().push(Received {
+                    line: format!("{} {}", method, path),
+                    content_type,
+                    body: serde_json::from_slice(&body).ok(),
+                });
+                Response::builder()
+                    .status(status)
+                    .body(Body::from(serde_json::to_vec(&response).unwrap()))
+            }
+        });
+
+        let client = KubeClient {
+            client: Client::new(service, "default"),
+            timeout: None,
+            field_manager: DEFAULT_FIELD_MANAGER.to_string(),
+            apply_strategy: ApplyStrategy::default(),
+            immutable: false,
+            default_namespace: None,
+        };
+        (client, log)
+    }
+
+    fn secret(name: &str, secret_type: &str) -> (StatusCode, Value) {
+        let body = json!({
+            "apiVersion": "v1",
+            "kind": "Secret",
+            "metadata": { "name": name, "namespace": "apps" },
+            "type": secret_type,
+        });
+        (StatusCode::OK, body)
+    }
+
+    fn not_found() -> (StatusCode, Value) {
+        let body = json!({
+            "apiVersion": "v1",
+            "kind": "Status",
+            "status": "Failure",
+            "message": "secrets not found",
+            "reason": "NotFound",
+            "code": 404,
+        });
+        (StatusCode::NOT_FOUND, body)
+    }
+
+    async fn apply(client: &KubeClient, secret_type: &str) -> Result<()> {
+        let data = BTreeMap::from([("password".to_string(), b"hunter2".to_vec())]);
+        client
+            .apply_secret(
+                "apps",
+                "db",
+                data,
+                secret_type,
+                &BTreeMap::new(),
+                &BTreeMap::new(),
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn apply_secret_patches_in_place() {
+        let (client, log) = mock_client(|_, _| secret("db", "Opaque"));
+
+        apply(&client, "Opaque").await.unwrap();
+
+        // A single request: the type isn't looked up before applying
+        assert_eq!(lines(&log), ["PATCH /api/v1/namespaces/apps/secrets/db"]);
+    }
+
+    #[tokio::test]
+    async fn apply_secret_creates_missing_secret_with_patch() {
+        let (client, log) = mock_client(|method, path| match *method {
+            // Server-side apply creates the secret if it doesn't exist
+            Method::PATCH => (StatusCode::CREATED, secret("db", "Opaque").1),
+            _ => panic!("unexpected request: {} {}", method, path),
+        });
+
+        apply(&client, "Opaque").await.unwrap();
+
+        assert_eq!(lines(&log), ["PATCH /api/v1/namespaces/apps/secrets/db"]);
+    }
+
+    #[test]
+    fn managed_selector_requires_source_labels() {
+        let labels = BTreeMap::from([
+            ("ksecret.io/environment".to_string(), "dev".to_string()),
+            ("ksecret.io/prefix".to_string(), "k8s".to_string()),
+        ]);
+
+        assert_eq!(
+            managed_selector(&labels),
+            "app.kubernetes.io/managed-by=ksecret,ksecret.io/environment=dev,ksecret.io/prefix=k8s"
+        );
+        assert_eq!(
+            managed_selector(&BTreeMap::new()),
+            "app.kubernetes.io/managed-by=ksecret"
+        );
+    }
+
+    /// A client whose secret is still found by the first `pending_gets` GETs
+    /// after being deleted
+    fn deleting_client(pending_gets: u32) -> (KubeClient, RequestLog) {
+        let gets = AtomicU32::new(0);
+        let (client, log) = mock_client(move |method, _| match *method {
+            Method::GET if gets.fetch_add(1, Ordering::SeqCst) >= pending_gets => not_found(),
+            _ => secret("db", "Opaque"),
+        });
+        (client.with_apply_strategy(ApplyStrategy::Recreate), log)
+    }
+
+    #[tokio::test]
+    async fn recreate_waits_for_delayed_deletion() {
+        let (client, log) = deleting_client(2);
+
+        apply(&client, "Opaque").await.unwrap();
+
+        let log = lines(&log);
+        let methods: Vec<_> = log.iter().map(|r| r.split(' ').next().unwrap()).collect();
+        assert_eq!(methods, ["DELETE", "GET", "GET", "GET", "POST"]);
+    }
+
+    #[tokio::test]
+    async fn recreate_accepts_deletion_seen_on_last_poll() {
+        let (client, log) = deleting_client(DELETE_POLL_ATTEMPTS - 1);
+
+        apply(&client, "Opaque").await.unwrap();
+
+        let log = lines(&log);
+        assert_eq!(log.last().unwrap(), "POST /api/v1/namespaces/apps/secrets");
+    }
+
+    #[tokio::test]
+    async fn recreate_gives_up_on_stuck_deletion() {
+        let (client, log) = deleting_client(u32::MAX);
+
+        let err = apply(&client, "Opaque").await.unwrap_err();
+
+        assert!(
+            format!("{:#}", err).contains("still being deleted"),
+            "{:#}",
+            err
+        );
+        let log = lines(&log);
+        let gets = log.iter().filter(|r| r.starts_with("GET")).count();
+        assert_eq!(gets, DELETE_POLL_ATTEMPTS as usize);
+        assert!(!log.iter().any(|r| r.starts_with("POST")));
+    }
+
+    #[tokio::test]
+    async fn apply_without_server_side_apply_recreates() {
+        let gets = AtomicU32::new(0);
+        let (client, log) = mock_client(move |method, _| match *method {
+            Method::PATCH => {
+                let body = json!({
+                    "apiVersion": "v1",
+                    "kind": "Status",
+                    "status": "Failure",
+                    "message": "the server does not support apply patches",
+                    "reason": "UnsupportedMediaType",
+                    "code": 415,
+                });
+                (StatusCode::UNSUPPORTED_MEDIA_TYPE, body)
+            }
+            Method::GET if gets.fetch_add(1, Ordering::SeqCst) >= 1 => not_found(),
+            _ => secret("db", "Opaque"),
+        });
+
+        apply(&client, "Opaque").await.unwrap();
+
+        assert_eq!(
+            lines(&log),
+            [
+                "PATCH /api/v1/namespaces/apps/secrets/db",
+                "DELETE /api/v1/namespaces/apps/secrets/db",
+                "GET /api/v1/namespaces/apps/secrets/db",
+                "GET /api/v1/namespaces/apps/secrets/db",
+                "POST /api/v1/namespaces/apps/secrets",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn apply_secret_leaves_foreign_annotations_in_place() {
+        let (client, log) = mock_client(|_, _| {
+            let (status, mut body) = secret("db", "Opaque");
+            body["metadata"]["annotations"] = json!({ "team.example.com/owner": "payments" });
+            (status, body)
+        });
+        let annotations =
+            BTreeMap::from([("ksecret.io/source-version".to_string(), "3".to_string())]);
+
+        client
+            .apply_secret(
+                "apps",
+                "db",
+                BTreeMap::new(),
+                "Opaque",
+                &BTreeMap::new(),
+                &annotations,
+            )
+            .await
+            .unwrap();
+
+        // A server-side apply only touches the fields it sends, so the other
+        // tool's annotation is kept by the API server
+        let log = log.lock().unwrap();
+        let patch = log.iter().find(|r| r.line.starts_with("PATCH")).unwrap();
+        assert_eq!(
+            patch.content_type.as_deref(),
+            Some("application/apply-patch+yaml")
+        );
+        let sent = &patch.body.as_ref().unwrap()["metadata"]["annotations"];
+        assert_eq!(sent, &json!({ "ksecret.io/source-version": "3" }));
+        assert!(!log.iter().any(|r| r.line.starts_with("PUT")));
+    }
+
+    #[tokio::test]
+    async fn recreate_strategy_deletes_then_creates() {
+        let (client, log) = deleting_client(0);
+
+        apply(&client, "Opaque").await.unwrap();
+
+        assert_eq!(
+            lines(&log),
+            [
+                "DELETE /api/v1/namespaces/apps/secrets/db",
+                "GET /api/v1/namespaces/apps/secrets/db",
+                "POST /api/v1/namespaces/apps/secrets",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn recreate_strategy_creates_missing_secret() {
+        let (client, log) = mock_client(|method, _| match *method {
+            Method::DELETE => not_found(),
+            _ => secret("db", "Opaque"),
+        });
+        let client = client.with_apply_strategy(ApplyStrategy::Recreate);
+
+        apply(&client, "Opaque").await.unwrap();
+
+        assert_eq!(
+            lines(&log),
+            [
+                "DELETE /api/v1/namespaces/apps/secrets/db",
+                "POST /api/v1/namespaces/apps/secrets",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn changed_type_is_recreated() {
+        let (client, log) = mock_client(|method, _| match *method {
+            Method::PATCH => {
+                let body = json!({
+                    "apiVersion": "v1",
+                    "kind": "Status",
+                    "status": "Failure",
+                    "message": "Secret \"db\" is invalid: type: Invalid value: \"kubernetes.io/tls\": field is immutable",
+                    "reason": "Invalid",
+                    "code": 422,
+                });
+                (StatusCode::UNPROCESSABLE_ENTITY, body)
+            }
+            Method::GET => not_found(),
+            _ => secret("db", "kubernetes.io/tls"),
+        });
+
+        apply(&client, "kubernetes.io/tls").await.unwrap();
+
+        assert_eq!(
+            lines(&log),
+            [
+                "PATCH /api/v1/namespaces/apps/secrets/db",
+                "DELETE /api/v1/namespaces/apps/secrets/db",
+                "GET /api/v1/namespaces/apps/secrets/db",
+                "POST /api/v1/namespaces/apps/secrets",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn unreachable_api_server_times_out() {
+        let _ = rustls::crypto::ring::default_provider().install_default();
+        // Accepts connections but never answers, like a black-holed endpoint
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("https://{}", listener.local_addr().unwrap());
+        let timeouts = KubeTimeouts {
+            operation: None,
+            connect: Some(Duration::from_secs(1)),
+            request: Some(Duration::from_secs(1)),
+        };
+        let client =
+            KubeClient::from_config(Config::new(url.parse().unwrap()), timeouts, None).unwrap();
+
+        let started = std::time::Instant::now();
+        let err = client.namespace_exists("apps").await.unwrap_err();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(err.chain().any(|e| e.is::<KubeTimeoutError>()), "{:#}", err);
+    }
+}
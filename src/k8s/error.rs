@@ -1,16 +1,35 @@
 use anyhow::{anyhow, Error};
+use kube::config::KubeconfigError;
 use kube::Error as KubeError;
 
+use crate::error::{ApiError, ErrorKind};
+
+/// The Kubernetes API server didn't accept a connection or answer a request
+/// within the configured connect / request timeout
+#[derive(Debug, thiserror::Error)]
+#[error("Kubernetes API server timed out: {0}\nCheck that the cluster is reachable (VPN, firewall, kube context), or raise k8s_connect_timeout_seconds / k8s_request_timeout_seconds.")]
+pub struct KubeTimeoutError(String);
+
 pub fn map_k8s_error(err: Error) -> Error {
     if let Some(kube_err) = err.downcast_ref::<KubeError>() {
+        if is_timed_out(kube_err) {
+            return KubeTimeoutError(kube_err.to_string()).into();
+        }
         return match kube_err {
             KubeError::Api(api_err) => {
-                match api_err.code {
+                let hint = match api_err.code {
                     401 => anyhow!("Kubernetes Authentication failed.\nCheck your kubeconfig credentials."),
                     403 => anyhow!("Kubernetes Permission denied.\nYou don't have permission to perform this action in the namespace."),
                     404 => anyhow!("Kubernetes Resource not found."),
                     _ => anyhow!("Kubernetes API Error: {}", api_err.message),
+                };
+                ApiError {
+                    service: "kubernetes",
+                    code: api_err.code.to_string(),
+                    kind: ErrorKind::from_http_status(api_err.code),
+                    message: hint.to_string(),
                 }
+                .into()
             }
             // Kubeconfig error is likely wrapped in a different variant or needs to be matched differently
             // Looking at kube-rs docs for v0.98, the error enum structure might have changed or I was using old variants
@@ -18,5 +37,32 @@ pub fn map_k8s_error(err: Error) -> Error {
             _ => anyhow!("Kubernetes Error: {}", kube_err),
         };
     }
+    if let Some(config_err) = err.downcast_ref::<KubeconfigError>() {
+        let hint = match config_err {
+            KubeconfigError::FindPath | KubeconfigError::ReadConfig(..) => {
+                "No kubeconfig could be read.\nSet KUBECONFIG or create ~/.kube/config (e.g. with 'gcloud container clusters get-credentials <CLUSTER>')."
+            }
+            KubeconfigError::Parse(_) | KubeconfigError::InvalidStructure(_) => {
+                "The kubeconfig is not a valid kubeconfig file.\nCheck the file named by KUBECONFIG (or ~/.kube/config)."
+            }
+            _ => return anyhow!("Kubeconfig Error: {}", config_err),
+        };
+        return anyhow!("Kubeconfig Error: {}\n{}", config_err, hint);
+    }
     err
 }
+
+/// Whether an I/O error anywhere in the chain timed out, as kube's connect
+/// and read / write timeouts report it
+fn is_timed_out(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(err);
+    while let Some(e) = source {
+        if e.downcast_ref::<std::io::Error>()
+            .is_some_and(|io| io.kind() == std::io::ErrorKind::TimedOut)
+        {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
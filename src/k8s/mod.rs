@@ -1,4 +1,6 @@
 pub mod client;
 pub mod error;
+pub mod target;
 
 pub use client::KubeClient;
+pub use target::KubeTarget;
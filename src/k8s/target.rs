@@ -0,0 +1,64 @@
+use anyhow::{Context, Result};
+
+use crate::config::Config;
+use crate::k8s::client::KubeTimeouts;
+use crate::k8s::KubeClient;
+
+/// The cluster and namespace a command works against.
+///
+/// Shared by every command that reads or writes Kubernetes secrets so the
+/// context and namespace defaults are the same everywhere.
+#[derive(Debug, Clone)]
+pub struct KubeTarget {
+    /// Kubernetes context to use (`None` for the current context)
+    pub context: Option<String>,
+    /// Target namespace
+    pub namespace: String,
+    /// Replace a defaulted namespace with the context's namespace on connect
+    namespace_from_context: bool,
+}
+
+impl KubeTarget {
+    /// Target `namespace` in `context`; the namespace defaults to the
+    /// environment name
+    pub fn new(environment: &str, namespace: Option<String>, context: Option<String>) -> Self {
+        Self {
+            context,
+            namespace_from_context: false,
+            namespace: namespace.unwrap_or_else(|| environment.to_string()),
+        }
+    }
+
+    /// Target `namespace` in `context`. Without an explicit namespace, the
+    /// namespace of the context is used when `from_context` is set and it has
+    /// one, and the environment name otherwise.
+    pub fn with_context_namespace(
+        environment: &str,
+        namespace: Option<String>,
+        context: Option<String>,
+        from_context: bool,
+    ) -> Self {
+        let namespace_from_context = from_context && namespace.is_none();
+        Self {
+            namespace_from_context,
+            ..Self::new(environment, namespace, context)
+        }
+    }
+
+    /// Connect to the target cluster, honoring `in_cluster` and the timeout
+    /// from config, and resolve the namespace from the context if requested
+    pub async fn connect(&mut self, config: &Config) -> Result<KubeClient> {
+        let timeouts = KubeTimeouts::from_config(config);
+        let client = KubeClient::new(self.context.as_deref(), config.in_cluster, timeouts)
+            .await
+            .context("Failed to initialize Kubernetes client")?;
+
+        if self.namespace_from_context {
+            if let Some(namespace) = client.default_namespace() {
+                self.namespace = namespace.to_string();
+            }
+        }
+
+        Ok(client)
+    }
+}
@@ -1,5 +1,11 @@
 pub mod commands;
 pub mod config;
+pub mod error;
 pub mod gcp;
 pub mod k8s;
+pub mod output;
+pub mod timeout;
 pub mod cache;
+pub mod metrics;
+pub mod telemetry;
+pub mod content;
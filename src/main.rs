@@ -1,13 +1,20 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use colored::Colorize;
+use std::io::IsTerminal;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod commands;
 mod config;
+mod error;
 mod gcp;
 mod k8s;
+mod output;
+mod timeout;
 mod cache;
+mod metrics;
+mod telemetry;
+mod content;
 
 /// ksecret - Kubernetes Secrets Management Tool
 ///
@@ -17,15 +24,80 @@ mod cache;
 #[derive(Parser)]
 #[command(name = "ksecret")]
 #[command(author, version, about, long_about = None)]
+#[command(after_help = "Exit codes:\n  \
+    0  Success\n  \
+    1  Error\n  \
+    2  Not found (secret, namespace, ...)\n  \
+    3  Permission denied\n  \
+    4  Authentication failed\n  \
+    64 Invalid command line (usage error)")]
 struct Cli {
     /// Enable verbose output
     #[arg(short, long, global = true)]
     verbose: bool,
 
+    /// Suppress progress output (errors and requested data are still printed)
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Disable colored output (also honors NO_COLOR)
+    #[arg(long, global = true)]
+    no_color: bool,
+
     /// Google Cloud Project ID (overrides config file)
     #[arg(long, env = "KSECRET_GCP_PROJECT")]
     project: Option<String>,
 
+    /// Prefix of GCP secret names (overrides config file)
+    #[arg(long, global = true, env = "KSECRET_SECRET_PREFIX")]
+    prefix: Option<String>,
+
+    /// Service account to impersonate for GCP calls (overrides config file)
+    #[arg(
+        long,
+        global = true,
+        env = "KSECRET_IMPERSONATE_SERVICE_ACCOUNT",
+        value_name = "EMAIL"
+    )]
+    impersonate_service_account: Option<String>,
+
+    /// Use the in-cluster service account for Kubernetes, ignoring any kubeconfig
+    #[arg(
+        long,
+        global = true,
+        env = "KSECRET_IN_CLUSTER",
+        value_parser = clap::builder::FalseyValueParser::new()
+    )]
+    in_cluster: bool,
+
+    /// Format of log output (text, json)
+    #[arg(
+        long,
+        global = true,
+        env = "KSECRET_LOG_FORMAT",
+        default_value = "text",
+        value_parser = ["text", "json"]
+    )]
+    log_format: String,
+
+    /// Export traces to this OpenTelemetry collector (OTLP/HTTP, e.g.
+    /// http://localhost:4318)
+    #[arg(long, global = true, env = "KSECRET_OTLP_ENDPOINT", value_name = "URL")]
+    otlp_endpoint: Option<String>,
+
+    /// Format of error messages on stderr (human, json)
+    #[arg(
+        long,
+        global = true,
+        default_value = "human",
+        value_parser = ["human", "json"]
+    )]
+    error_format: String,
+
+    /// Timeout in seconds for each GCP / Kubernetes operation (overrides config file)
+    #[arg(long, global = true, value_name = "SECONDS")]
+    timeout: Option<u64>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -49,6 +121,184 @@ enum Commands {
         /// Perform a dry run without making changes
         #[arg(long)]
         dry_run: bool,
+
+        /// Only sync these secrets (comma-separated)
+        #[arg(long, value_delimiter = ',', conflicts_with = "except")]
+        only: Vec<String>,
+
+        /// Sync all secrets except these (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        except: Vec<String>,
+
+        /// Create the target namespace if it does not exist
+        #[arg(long)]
+        create_namespace: bool,
+
+        /// Delete managed Kubernetes secrets that no longer exist in GCP
+        #[arg(long)]
+        prune: bool,
+
+        /// Prune without asking for confirmation
+        #[arg(short, long, requires = "prune")]
+        yes: bool,
+
+        /// Kubernetes secret type (e.g. Opaque, tls, kubernetes.io/dockerconfigjson)
+        #[arg(long)]
+        secret_type: Option<String>,
+
+        /// Maximum number of secrets to sync concurrently
+        #[arg(long, default_value_t = commands::sync::DEFAULT_CONCURRENCY)]
+        concurrency: usize,
+
+        /// Keep syncing the remaining secrets when one fails
+        #[arg(long)]
+        continue_on_error: bool,
+
+        /// Extra label for the Kubernetes secrets (repeatable)
+        #[arg(long = "label", value_name = "KEY=VALUE")]
+        labels: Vec<String>,
+
+        /// Extra annotation for the Kubernetes secrets (repeatable)
+        #[arg(long = "annotation", value_name = "KEY=VALUE")]
+        annotations: Vec<String>,
+
+        /// Keep running and re-apply secrets whose value changed
+        #[arg(long, conflicts_with = "dry_run")]
+        watch: bool,
+
+        /// Server-side apply field manager for the Kubernetes secrets
+        #[arg(long, value_name = "NAME")]
+        field_manager: Option<String>,
+
+        /// How secrets are written: "patch" updates them in place with
+        /// server-side apply; "recreate" deletes and re-creates them, for
+        /// admission controllers that reject updates, but leaves each secret
+        /// briefly missing and drops labels other tools added
+        #[arg(long, default_value = "patch", value_parser = ["patch", "recreate"])]
+        apply_strategy: String,
+
+        /// Mark the Kubernetes secrets immutable; a changed secret is deleted
+        /// and re-created, since immutable data can't be updated in place
+        #[arg(long)]
+        immutable: bool,
+
+        /// Seconds between sync cycles in --watch mode
+        #[arg(long, default_value_t = 60, value_name = "SECONDS", requires = "watch")]
+        interval: u64,
+
+        /// Expand nested JSON/YAML objects and arrays into PARENT_CHILD keys
+        #[arg(long)]
+        flatten: bool,
+
+        /// Separator between key parts with --flatten
+        #[arg(long, default_value = "_", value_name = "SEP", requires = "flatten")]
+        flatten_separator: String,
+
+        /// Store a value that is a single PEM, kubeconfig, service account key
+        /// or Docker config under its conventional key (e.g. tls.crt) instead
+        /// of "value"
+        #[arg(long, conflicts_with = "combine")]
+        smart_keys: bool,
+
+        /// Keep keys of existing Kubernetes secrets that ksecret didn't write,
+        /// replacing only the keys it manages
+        #[arg(long, conflicts_with = "combine")]
+        merge: bool,
+
+        /// Read each secret back after applying it and fail if its data
+        /// doesn't match (SHA-256)
+        #[arg(long)]
+        verify: bool,
+
+        /// If a secret's latest version is disabled or destroyed, sync its
+        /// newest enabled version instead
+        #[arg(long)]
+        resolve_enabled: bool,
+
+        /// Write the keys of every secret into this single Kubernetes secret
+        #[arg(long, value_name = "SECRET_NAME", conflicts_with_all = ["watch", "prune"])]
+        combine: Option<String>,
+
+        /// With --combine, prefix keys of multi-value secrets with the source
+        /// secret's name (NAME_KEY) so they can't collide
+        #[arg(long, requires = "combine")]
+        prefix_keys: bool,
+
+        /// Without --namespace, sync to the namespace set on the kube context
+        /// (falling back to the environment name)
+        #[arg(long, conflicts_with = "namespace")]
+        namespace_from_context: bool,
+
+        /// Output format: "text" progress lines, or "json" for a single
+        /// summary of every secret's outcome
+        #[arg(
+            short,
+            long,
+            default_value = "text",
+            value_parser = ["text", "json"],
+            conflicts_with_all = ["watch", "combine"]
+        )]
+        output: String,
+
+        /// Write Prometheus metrics about the run to this file (for the
+        /// node_exporter textfile collector)
+        #[arg(long, value_name = "PATH", conflicts_with = "watch")]
+        metrics_file: Option<std::path::PathBuf>,
+
+        /// List secrets from GCP instead of using the cached listing
+        #[arg(long)]
+        refresh: bool,
+    },
+
+    /// Show what a sync would change in a Kubernetes namespace
+    Diff {
+        /// Environment name
+        #[arg(short, long, required = true)]
+        env: String,
+
+        /// Target Kubernetes namespace (defaults to environment name)
+        #[arg(short, long)]
+        namespace: Option<String>,
+
+        /// Kubernetes context to use (defaults to current context)
+        #[arg(short, long)]
+        context: Option<String>,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        output: String,
+
+        /// Show a short hash of old and new values, keyed per run (default
+        /// in CI or when output is not a terminal)
+        #[arg(long, conflicts_with = "show_values")]
+        mask: bool,
+
+        /// Show the full old and new values of changed keys
+        #[arg(long)]
+        show_values: bool,
+    },
+
+    /// Sync an environment to the same namespace in several clusters
+    Replicate {
+        /// Environment name
+        #[arg(short, long, required = true)]
+        env: String,
+
+        /// Target Kubernetes namespace (defaults to environment name)
+        #[arg(short, long)]
+        namespace: Option<String>,
+
+        /// Kubernetes context to sync to (repeatable)
+        #[arg(short, long = "context", value_name = "CONTEXT", required = true)]
+        contexts: Vec<String>,
+
+        /// Perform a dry run without making changes
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Create the target namespace if it does not exist
+        #[arg(long)]
+        create_namespace: bool,
     },
 
     /// Get a secret value from Google Cloud Secret Manager
@@ -61,13 +311,36 @@ enum Commands {
         #[arg(short, long, required = true)]
         env: String,
 
-        /// Output format (text, json)
+        /// Output format (text, json, yaml, env)
         #[arg(short, long, default_value = "text")]
         output: String,
 
+        /// Secret version to read (a version number or "latest")
+        #[arg(long, default_value = "latest")]
+        version: String,
+
         /// Skip cache and fetch directly from GCP
         #[arg(long)]
         no_cache: bool,
+
+        /// Print each key a sync would write, flattening JSON/YAML secrets
+        #[arg(long)]
+        all_keys: bool,
+
+        /// If the latest version is disabled or destroyed, read the newest
+        /// enabled version instead
+        #[arg(long)]
+        resolve_enabled: bool,
+
+        /// Print nothing and exit 0 if the secret (or version) doesn't exist;
+        /// any other error still fails
+        #[arg(long)]
+        ignore_missing: bool,
+
+        /// Print this value and exit 0 if the secret (or version) doesn't
+        /// exist; any other error still fails
+        #[arg(long, value_name = "VALUE")]
+        default: Option<String>,
     },
 
     /// Set a secret value in Google Cloud Secret Manager
@@ -81,16 +354,141 @@ enum Commands {
         env: String,
 
         /// Secret value (will prompt if not provided)
-        #[arg(long)]
+        #[arg(long, conflicts_with_all = ["stdin", "from_file", "from_env"])]
         value: Option<String>,
 
         /// Read value from stdin
-        #[arg(long)]
+        #[arg(long, conflicts_with_all = ["from_file", "from_env"])]
         stdin: bool,
+
+        /// Keep trailing whitespace and newlines of a --stdin or prompted
+        /// value instead of trimming them
+        #[arg(long, conflicts_with_all = ["value", "from_file", "from_env"])]
+        no_trim: bool,
+
+        /// Read value from a file, stored as-is without trimming
+        #[arg(long, value_name = "PATH", conflicts_with = "from_env")]
+        from_file: Option<String>,
+
+        /// Read value from an environment variable, stored as-is; keeps it
+        /// out of the process list, unlike --value
+        #[arg(long, value_name = "VARNAME")]
+        from_env: Option<String>,
+
+        /// With --from-env, store an empty value instead of failing
+        #[arg(long, requires = "from_env")]
+        allow_empty: bool,
+
+        /// Label to attach to the secret (repeatable)
+        #[arg(long = "label", value_name = "KEY=VALUE")]
+        labels: Vec<String>,
+
+        /// Free-form annotation to attach to the secret, e.g. an owner note
+        /// or ticket link (repeatable)
+        #[arg(long = "annotation", value_name = "KEY=VALUE")]
+        annotations: Vec<String>,
+
+        /// Set a key in a JSON multi-value secret, keeping other keys (repeatable)
+        #[arg(
+            long = "set-key",
+            value_name = "KEY=VALUE",
+            conflicts_with_all = ["value", "stdin", "from_file", "from_env"]
+        )]
+        set_keys: Vec<String>,
+
+        /// Remove a key from a JSON multi-value secret (repeatable)
+        #[arg(
+            long = "unset-key",
+            value_name = "KEY",
+            conflicts_with_all = ["value", "stdin", "from_file", "from_env"]
+        )]
+        unset_keys: Vec<String>,
+
+        /// Show what would be written without changing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Let GCP delete the secret after this long (e.g. 24h, 30d)
+        #[arg(long, value_name = "DURATION")]
+        expire_in: Option<String>,
+
+        /// Destroy the oldest enabled versions beyond this many (overrides config)
+        #[arg(long, value_name = "N")]
+        max_versions: Option<usize>,
+
+        /// Only create the secret; if it already exists, leave it unchanged
+        /// and exit successfully
+        #[arg(long, conflicts_with_all = ["set_keys", "unset_keys"])]
+        if_not_exists: bool,
+
+        /// With --if-not-exists, fail when the secret already exists
+        #[arg(long, requires = "if_not_exists")]
+        fail_if_exists: bool,
     },
 
     /// List all secrets for an environment
     List {
+        /// Environment name
+        #[arg(
+            short,
+            long,
+            required_unless_present = "all_environments",
+            conflicts_with = "all_environments"
+        )]
+        env: Option<String>,
+
+        /// List secrets of every environment, grouped by environment
+        #[arg(long)]
+        all_environments: bool,
+
+        /// Output format (table, json, yaml)
+        #[arg(short, long, default_value = "table")]
+        output: String,
+
+        /// Only show secrets with this label (repeatable, all must match)
+        #[arg(long, value_name = "KEY=VALUE")]
+        label_selector: Vec<String>,
+
+        /// Only show secrets whose name matches this regular expression
+        #[arg(long, value_name = "PATTERN")]
+        regex: Option<String>,
+
+        /// Show a LABELS column in table output
+        #[arg(long)]
+        show_labels: bool,
+
+        /// Sort secrets by name or creation time
+        #[arg(long, default_value = "name", value_parser = ["name", "created"])]
+        sort: String,
+
+        /// Reverse the sort order
+        #[arg(long)]
+        reverse: bool,
+
+        /// Print only secret names, one per line, for scripting
+        #[arg(long, conflicts_with_all = ["output", "show_labels", "all_environments"])]
+        names_only: bool,
+
+        /// Only list secrets created since a duration ago (7d) or a UTC date
+        /// (2024-01-01) / RFC 3339 timestamp
+        #[arg(long, value_name = "DURATION|DATE")]
+        since: Option<String>,
+
+        /// Skip the cached listing and list directly from GCP
+        #[arg(long, conflicts_with = "refresh")]
+        no_cache: bool,
+
+        /// List from GCP and replace the cached listing
+        #[arg(long)]
+        refresh: bool,
+    },
+
+    /// List all versions of a secret
+    Versions {
+        /// Secret name
+        #[arg(value_name = "NAME")]
+        name: String,
+
         /// Environment name
         #[arg(short, long, required = true)]
         env: String,
@@ -100,8 +498,8 @@ enum Commands {
         output: String,
     },
 
-    /// Delete a secret from Google Cloud Secret Manager
-    Delete {
+    /// Show a chronological audit timeline of a secret's versions
+    Audit {
         /// Secret name
         #[arg(value_name = "NAME")]
         name: String,
@@ -110,74 +508,800 @@ enum Commands {
         #[arg(short, long, required = true)]
         env: String,
 
+        /// Output format (table, json)
+        #[arg(short, long, default_value = "table")]
+        output: String,
+    },
+
+    /// Restore a previous version of a secret as the new latest version
+    Rollback {
+        /// Secret name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Environment name
+        #[arg(short, long, required = true)]
+        env: String,
+
+        /// Version number to restore
+        #[arg(long, required = true)]
+        to_version: String,
+    },
+
+    /// Delete a secret from Google Cloud Secret Manager
+    Delete {
+        /// Secret name
+        #[arg(
+            value_name = "NAME",
+            required_unless_present = "all",
+            conflicts_with = "all"
+        )]
+        name: Option<String>,
+
+        /// Environment name
+        #[arg(short, long, required = true)]
+        env: String,
+
+        /// Delete every secret of the environment (narrow with --regex)
+        #[arg(long)]
+        all: bool,
+
+        /// With --all, only delete secrets whose name matches this regular
+        /// expression
+        #[arg(long, value_name = "PATTERN", requires = "all")]
+        regex: Option<String>,
+
         /// Skip confirmation prompt
         #[arg(short, long)]
         force: bool,
+
+        /// Show what would be deleted without calling the API
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Grant a member access to a secret
+    Grant {
+        /// Secret name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Environment name
+        #[arg(short, long, required = true)]
+        env: String,
+
+        /// Member to grant the role to (e.g. user:jane@example.com, serviceAccount:...)
+        #[arg(long, required = true)]
+        member: String,
+
+        /// IAM role to grant
+        #[arg(long, default_value = "roles/secretmanager.secretAccessor")]
+        role: String,
+    },
+
+    /// Revoke a member's access to a secret
+    Revoke {
+        /// Secret name
+        #[arg(value_name = "NAME")]
+        name: String,
+
+        /// Environment name
+        #[arg(short, long, required = true)]
+        env: String,
+
+        /// Member to revoke the role from (e.g. user:jane@example.com, serviceAccount:...)
+        #[arg(long, required = true)]
+        member: String,
+
+        /// IAM role to revoke
+        #[arg(long, default_value = "roles/secretmanager.secretAccessor")]
+        role: String,
+    },
+
+    /// Rename a secret within an environment
+    Rename {
+        /// Current secret name
+        #[arg(value_name = "OLD_NAME")]
+        old_name: String,
+
+        /// New secret name
+        #[arg(value_name = "NEW_NAME")]
+        new_name: String,
+
+        /// Environment name
+        #[arg(short, long, required = true)]
+        env: String,
+
+        /// Keep the old secret instead of deleting it
+        #[arg(long)]
+        keep_old: bool,
+
+        /// Overwrite the new secret if it already exists
+        #[arg(long)]
+        overwrite: bool,
+    },
+
+    /// Copy secrets from one environment to another
+    Copy {
+        /// Secret name (omit when using --all)
+        #[arg(
+            value_name = "NAME",
+            required_unless_present = "all",
+            conflicts_with = "all"
+        )]
+        name: Option<String>,
+
+        /// Source environment
+        #[arg(long, required = true)]
+        from_env: String,
+
+        /// Destination environment
+        #[arg(long, required = true)]
+        to_env: String,
+
+        /// Copy every secret in the source environment
+        #[arg(long)]
+        all: bool,
+
+        /// Show what would be copied without making changes
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Overwrite secrets that already exist in the destination
+        #[arg(long)]
+        overwrite: bool,
+
+        /// Copy into another GCP project (needs write access there)
+        #[arg(long, value_name = "PROJECT_ID")]
+        to_project: Option<String>,
+
+        /// Copy every enabled version, oldest first, not just the latest
+        #[arg(long)]
+        all_versions: bool,
+    },
+
+    /// Move secrets from one prefix to another
+    Migrate {
+        /// Prefix the secrets are currently under
+        #[arg(long, required = true)]
+        from_prefix: String,
+
+        /// Prefix to recreate them under
+        #[arg(long, required = true)]
+        to_prefix: String,
+
+        /// Environment name
+        #[arg(
+            short,
+            long,
+            required_unless_present = "all_environments",
+            conflicts_with = "all_environments"
+        )]
+        env: Option<String>,
+
+        /// Migrate the secrets of every environment
+        #[arg(long)]
+        all_environments: bool,
+
+        /// Delete each original once its copy is confirmed
+        #[arg(long)]
+        delete_source: bool,
+
+        /// Show what would be migrated without making changes
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Replace secrets that already exist under the new prefix
+        #[arg(long)]
+        overwrite: bool,
+    },
+
+    /// Export all secrets for an environment (dotenv, json, yaml)
+    Export {
+        /// Environment name
+        #[arg(short, long, required = true)]
+        env: String,
+
+        /// Export format (dotenv, json, yaml)
+        #[arg(short, long, default_value = "dotenv")]
+        format: String,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Import secrets into an environment from a dotenv or JSON file
+    Import {
+        /// File to import
+        #[arg(value_name = "FILE")]
+        file: String,
+
+        /// Environment name
+        #[arg(short, long, required = true)]
+        env: String,
+
+        /// Input format (dotenv, json)
+        #[arg(short, long, default_value = "dotenv")]
+        format: String,
+
+        /// Prepended to every imported secret name (e.g. `db_`), unlike the
+        /// global --prefix, which sets the GCP secret prefix
+        #[arg(long)]
+        name_prefix: Option<String>,
+
+        /// Show what would be imported without making changes
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Generate shell completion scripts
+    ///
+    /// Prints a completion script for the given shell to stdout. Subcommands and
+    /// flags are completed; environment and secret names are not.
+    ///
+    /// Examples:
+    ///   ksecret completion bash > /etc/bash_completion.d/ksecret
+    ///   ksecret completion zsh > "${fpath[1]}/_ksecret"
+    ///   ksecret completion fish > ~/.config/fish/completions/ksecret.fish
+    ///   ksecret completion powershell >> $PROFILE
+    #[command(verbatim_doc_comment)]
+    Completion {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+
+    /// Print the environments that have secrets, one per line (for shell
+    /// completion scripts)
+    #[command(name = "__list-environments", hide = true)]
+    ListEnvironments,
+
+    /// Check configuration, GCP access and Kubernetes connectivity
+    Status {
+        /// Kubernetes namespace to check
+        #[arg(short, long, default_value = "default")]
+        namespace: String,
+
+        /// Kubernetes context to use (defaults to current context)
+        #[arg(short, long)]
+        context: Option<String>,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        output: String,
+    },
+
+    /// Diagnose common setup problems and suggest fixes
+    Doctor {
+        /// Kubernetes context to check (defaults to current context)
+        #[arg(short, long)]
+        context: Option<String>,
+
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        output: String,
+    },
+
+    /// Show which GCP principal ksecret authenticates as
+    Whoami {
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        output: String,
+    },
+
+    /// List the kube contexts available for --context
+    Contexts {
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        output: String,
+    },
+
+    /// Inspect or clear the local secret cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
     },
 
     /// Initialize configuration file
     Init {
-        /// Google Cloud Project ID
-        #[arg(long, required = true)]
-        project: String,
+        /// Google Cloud Project ID (prompted for when omitted on a terminal)
+        #[arg(long)]
+        project: Option<String>,
+
+        /// Overwrite an existing configuration file
+        #[arg(long)]
+        force: bool,
     },
 }
 
+#[derive(Subcommand)]
+enum CacheAction {
+    /// Remove cached secrets
+    Clear {
+        /// Only remove entries for this environment
+        #[arg(short, long)]
+        env: Option<String>,
+    },
+
+    /// Show the cache location and entry counts
+    Info,
+
+    /// List cached secrets and their expiry (values are not shown)
+    List,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
+    let matches = Cli::command()
+        .try_get_matches()
+        .unwrap_or_else(|e| exit_usage(e));
+    let command_name = matches.subcommand_name().unwrap_or_default().to_string();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| exit_usage(e));
+
+    // Initialize tracing; --verbose raises the default level to debug
+    let default_filter = if cli.verbose {
+        "ksecret=debug"
+    } else {
+        "ksecret=info"
+    };
+    let json_logs = cli.log_format == "json";
+    let tracer_provider = cli
+        .otlp_endpoint
+        .as_deref()
+        .map(telemetry::otlp_tracer_provider)
+        .transpose()?;
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "ksecret=info".into()),
+            std::env::var("RUST_LOG").unwrap_or_else(|_| default_filter.into()),
         ))
-        .with(tracing_subscriber::fmt::layer())
+        .with((!json_logs).then(tracing_subscriber::fmt::layer))
+        .with(json_logs.then(|| tracing_subscriber::fmt::layer().json()))
+        .with(
+            tracer_provider
+                .as_ref()
+                .map(|p| tracing_opentelemetry::layer().with_tracer(telemetry::tracer(p))),
+        )
         .init();
 
     // Install default crypto provider for rustls
     let _ = rustls::crypto::ring::default_provider().install_default();
+    let error_format = cli.error_format.clone();
 
-    let cli = Cli::parse();
+    // Quiet wins over verbose for stdout
+    output::set_quiet(cli.quiet);
+
+    // Disable color when asked to, or when stdout is piped somewhere
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    if cli.no_color || no_color_env || !std::io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
+
+    let result = run(cli).await;
+
+    // Flush buffered spans; exporting is best effort and never fails the command
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            tracing::debug!(error = %e, "Failed to flush traces");
+        }
+    }
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            if error_format == "json" {
+                eprintln!("{}", error_json(&command_name, &e));
+            } else {
+                eprintln!("{} {:#}", "Error:".red().bold(), e);
+            }
+            std::process::exit(error::error_kind(&e).exit_code());
+        }
+    }
+}
+
+/// Exit on a command line clap couldn't parse. Usage errors get their own exit
+/// code, since clap's default of 2 would read as "not found"; `--help` and
+/// `--version` still exit with 0.
+fn exit_usage(e: clap::Error) -> ! {
+    if e.use_stderr() {
+        let _ = e.print();
+        std::process::exit(error::USAGE_EXIT_CODE);
+    }
+    e.exit()
+}
+
+/// Dispatch the parsed command
+async fn run(cli: Cli) -> Result<()> {
+    // Load the config file lazily (init doesn't need one) and apply global overrides
+    let load_config = || -> Result<config::Config> {
+        let mut config = config::Config::load(cli.project.clone(), cli.prefix.clone())?;
+        if cli.timeout.is_some() {
+            config.timeout_seconds = cli.timeout;
+        }
+        if cli.in_cluster {
+            config.in_cluster = true;
+        }
+        if cli.impersonate_service_account.is_some() {
+            config.impersonate_service_account = cli.impersonate_service_account.clone();
+        }
+        Ok(config)
+    };
 
     // Execute command
-    let result = match cli.command {
+    match cli.command {
         Commands::Sync {
             environment,
             namespace,
             context,
             dry_run,
+            only,
+            except,
+            create_namespace,
+            prune,
+            yes,
+            secret_type,
+            concurrency,
+            continue_on_error,
+            labels,
+            annotations,
+            watch,
+            interval,
+            field_manager,
+            apply_strategy,
+            immutable,
+            flatten,
+            flatten_separator,
+            smart_keys,
+            merge,
+            verify,
+            resolve_enabled,
+            combine,
+            prefix_keys,
+            namespace_from_context,
+            output,
+            metrics_file,
+            refresh,
+        } => {
+            let config = load_config()?.for_environment(&environment);
+            let options = commands::sync::SyncOptions {
+                namespace,
+                context,
+                dry_run,
+                only,
+                except,
+                create_namespace,
+                prune,
+                yes,
+                secret_type,
+                concurrency,
+                continue_on_error,
+                labels,
+                annotations,
+                watch: watch.then_some(interval),
+                field_manager,
+                apply_strategy: apply_strategy.parse()?,
+                immutable,
+                flatten: flatten.then_some(flatten_separator),
+                smart_keys,
+                merge,
+                verify,
+                resolve_enabled,
+                combine,
+                prefix_keys,
+                namespace_from_context,
+                output,
+                metrics_file,
+                refresh,
+            };
+            commands::sync::execute(&config, &environment, options).await
+        }
+        Commands::Diff {
+            env,
+            namespace,
+            context,
+            output,
+            mask,
+            show_values,
+        } => {
+            let config = load_config()?.for_environment(&env);
+            commands::diff::execute(
+                &config,
+                &env,
+                namespace,
+                context,
+                &output,
+                mask,
+                show_values,
+            )
+            .await
+        }
+        Commands::Replicate {
+            env,
+            namespace,
+            contexts,
+            dry_run,
+            create_namespace,
         } => {
-            let config = config::Config::load(cli.project)?;
-            commands::sync::execute(&config, &environment, namespace, context, dry_run).await
+            let config = load_config()?.for_environment(&env);
+            commands::replicate::execute(
+                &config,
+                &env,
+                namespace,
+                &contexts,
+                dry_run,
+                create_namespace,
+            )
+            .await
         }
-        Commands::Get { name, env, output, no_cache } => {
-            let config = config::Config::load(cli.project)?;
-            commands::get::execute(&config, &name, &env, &output, no_cache).await
+        Commands::Get {
+            name,
+            env,
+            output,
+            version,
+            no_cache,
+            all_keys,
+            resolve_enabled,
+            ignore_missing,
+            default,
+        } => {
+            let config = load_config()?.for_environment(&env);
+            let options = commands::get::GetOptions {
+                output,
+                version,
+                no_cache,
+                all_keys,
+                resolve_enabled,
+                ignore_missing,
+                default,
+            };
+            commands::get::execute(&config, &name, &env, options).await
         }
         Commands::Set {
             name,
             env,
             value,
             stdin,
+            no_trim,
+            from_file,
+            from_env,
+            allow_empty,
+            labels,
+            annotations,
+            set_keys,
+            unset_keys,
+            dry_run,
+            expire_in,
+            max_versions,
+            if_not_exists,
+            fail_if_exists,
         } => {
-            let config = config::Config::load(cli.project)?;
-            commands::set::execute(&config, &name, &env, value, stdin).await
+            let config = load_config()?.for_environment(&env);
+            let options = commands::set::SetOptions {
+                value,
+                stdin,
+                no_trim,
+                from_file,
+                from_env,
+                allow_empty,
+                labels,
+                annotations,
+                set_keys,
+                unset_keys,
+                dry_run,
+                expire_in,
+                max_versions,
+                if_not_exists,
+                fail_if_exists,
+            };
+            commands::set::execute(&config, &name, &env, options).await
         }
-        Commands::List { env, output } => {
-            let config = config::Config::load(cli.project)?;
-            commands::list::execute(&config, &env, &output).await
+        Commands::List {
+            env,
+            all_environments: _,
+            output,
+            label_selector,
+            regex,
+            show_labels,
+            sort,
+            reverse,
+            names_only,
+            since,
+            no_cache,
+            refresh,
+        } => {
+            let config = load_config()?;
+            let options = commands::list::ListOptions {
+                output,
+                label_selector,
+                regex,
+                show_labels,
+                sort,
+                reverse,
+                names_only,
+                since,
+                no_cache,
+                refresh,
+            };
+            commands::list::execute(&config, env.as_deref(), options).await
         }
-        Commands::Delete { name, env, force } => {
-            let config = config::Config::load(cli.project)?;
-            commands::delete::execute(&config, &name, &env, force).await
+        Commands::Versions { name, env, output } => {
+            let config = load_config()?.for_environment(&env);
+            commands::versions::execute(&config, &name, &env, &output).await
         }
-        Commands::Init { project } => commands::init::execute(&project).await,
-    };
-
-    match result {
-        Ok(_) => Ok(()),
-        Err(e) => {
-            eprintln!("{} {:#}", "Error:".red().bold(), e);
-            std::process::exit(1);
+        Commands::Audit { name, env, output } => {
+            let config = load_config()?.for_environment(&env);
+            commands::audit::execute(&config, &name, &env, &output).await
+        }
+        Commands::Rollback {
+            name,
+            env,
+            to_version,
+        } => {
+            let config = load_config()?.for_environment(&env);
+            commands::rollback::execute(&config, &name, &env, &to_version).await
+        }
+        Commands::Delete {
+            name,
+            env,
+            all,
+            regex,
+            force,
+            dry_run,
+        } => {
+            let config = load_config()?.for_environment(&env);
+            match name {
+                Some(name) => commands::delete::execute(&config, &name, &env, force, dry_run).await,
+                None if all => {
+                    commands::delete::execute_all(&config, &env, regex.as_deref(), force, dry_run)
+                        .await
+                }
+                None => anyhow::bail!("Give a secret name or --all"),
+            }
+        }
+        Commands::Grant {
+            name,
+            env,
+            member,
+            role,
+        } => {
+            let config = load_config()?.for_environment(&env);
+            commands::iam::grant(&config, &name, &env, &member, &role).await
+        }
+        Commands::Revoke {
+            name,
+            env,
+            member,
+            role,
+        } => {
+            let config = load_config()?.for_environment(&env);
+            commands::iam::revoke(&config, &name, &env, &member, &role).await
+        }
+        Commands::Rename {
+            old_name,
+            new_name,
+            env,
+            keep_old,
+            overwrite,
+        } => {
+            let config = load_config()?.for_environment(&env);
+            commands::rename::execute(&config, &old_name, &new_name, &env, keep_old, overwrite)
+                .await
+        }
+        Commands::Copy {
+            name,
+            from_env,
+            to_env,
+            all: _,
+            dry_run,
+            overwrite,
+            to_project,
+            all_versions,
+        } => {
+            let config = load_config()?;
+            let options = commands::copy::CopyOptions {
+                dry_run,
+                overwrite,
+                to_project,
+                all_versions,
+            };
+            commands::copy::execute(&config, name.as_deref(), &from_env, &to_env, options).await
+        }
+        Commands::Migrate {
+            from_prefix,
+            to_prefix,
+            env,
+            all_environments: _,
+            delete_source,
+            dry_run,
+            overwrite,
+        } => {
+            let config = load_config()?;
+            let options = commands::migrate::MigrateOptions {
+                delete_source,
+                dry_run,
+                overwrite,
+            };
+            commands::migrate::execute(&config, &from_prefix, &to_prefix, env.as_deref(), options)
+                .await
+        }
+        Commands::Export {
+            env,
+            format,
+            output,
+        } => {
+            let config = load_config()?.for_environment(&env);
+            commands::export::execute(&config, &env, &format, output.as_deref()).await
+        }
+        Commands::Import {
+            file,
+            env,
+            format,
+            name_prefix,
+            dry_run,
+        } => {
+            let config = load_config()?.for_environment(&env);
+            commands::import::execute(
+                &config,
+                &env,
+                &file,
+                &format,
+                name_prefix.as_deref(),
+                dry_run,
+            )
+            .await
+        }
+        Commands::Status {
+            namespace,
+            context,
+            output,
+        } => {
+            let config = load_config()?;
+            commands::status::execute(&config, &namespace, context.as_deref(), &output).await
+        }
+        Commands::Doctor { context, output } => {
+            // A missing or broken config is one of the things diagnosed
+            commands::doctor::execute(load_config(), context.as_deref(), &output).await
+        }
+        Commands::Whoami { output } => {
+            let config = load_config()?;
+            commands::whoami::execute(&config, &output).await
+        }
+        Commands::Contexts { output } => commands::contexts::execute(&output),
+        Commands::Cache { action } => {
+            // The cache works without a config file; config only tunes the TTL
+            let config = load_config().unwrap_or_default();
+            match action {
+                CacheAction::Clear { env } => commands::cache::clear(&config, env.as_deref()),
+                CacheAction::Info => commands::cache::info(&config),
+                CacheAction::List => commands::cache::list(&config),
+            }
+        }
+        Commands::Init { project, force } => {
+            commands::init::execute(project.as_deref(), cli.prefix.as_deref(), force).await
+        }
+        Commands::Completion { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "ksecret", &mut std::io::stdout());
+            Ok(())
+        }
+        Commands::ListEnvironments => {
+            let config = load_config()?;
+            commands::environments::execute(&config).await
         }
     }
 }
+
+/// Serialize an error chain for `--error-format json`
+fn error_json(command: &str, err: &anyhow::Error) -> serde_json::Value {
+    let api_error = err
+        .chain()
+        .find_map(|e| e.downcast_ref::<error::ApiError>());
+
+    serde_json::json!({
+        "command": command,
+        "error": err.to_string(),
+        "causes": err.chain().skip(1).map(|e| e.to_string()).collect::<Vec<_>>(),
+        "service": api_error.map(|e| e.service),
+        "code": api_error.map(|e| e.code.as_str()),
+        "kind": error::error_kind(err).as_str(),
+    })
+}
@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Metric carried over from the previous file when a run fails
+const LAST_SUCCESS_METRIC: &str = "ksecret_sync_last_success_timestamp";
+
+/// Outcome of one sync run, as written to a metrics file
+#[derive(Debug, Default)]
+pub struct SyncMetrics {
+    /// Secrets selected for syncing
+    pub secrets: usize,
+    /// Secrets that failed to sync
+    pub failures: usize,
+}
+
+/// Write `metrics` for `environment` in the Prometheus text format, for the
+/// node_exporter textfile collector.
+///
+/// The file is written to a temporary file next to `path` and renamed over
+/// it, so the collector never reads a partial file. The last success
+/// timestamp is carried over from the previous file when the run failed.
+pub fn write_sync_metrics(
+    path: &Path,
+    environment: &str,
+    metrics: &SyncMetrics,
+    success: bool,
+) -> Result<()> {
+    let labels = format!("{{environment=\"{}\"}}", escape_label(environment));
+    let now = chrono::Utc::now().timestamp();
+    let last_success = if success {
+        Some(now)
+    } else {
+        previous_last_success(path, &labels)
+    };
+
+    let mut text = String::new();
+    let mut gauge = |name: &str, help: &str, value: i64| {
+        let _ = writeln!(text, "# HELP {} {}", name, help);
+        let _ = writeln!(text, "# TYPE {} gauge", name);
+        let _ = writeln!(text, "{}{} {}", name, labels, value);
+    };
+    gauge(
+        "ksecret_sync_secrets_total",
+        "Secrets selected by the last sync run.",
+        metrics.secrets as i64,
+    );
+    gauge(
+        "ksecret_sync_failures_total",
+        "Secrets that failed to sync in the last run.",
+        metrics.failures as i64,
+    );
+    gauge(
+        "ksecret_sync_success",
+        "Whether the last sync run succeeded (1) or failed (0).",
+        success as i64,
+    );
+    gauge(
+        "ksecret_sync_last_run_timestamp",
+        "Unix time of the last sync run.",
+        now,
+    );
+    if let Some(timestamp) = last_success {
+        gauge(
+            LAST_SUCCESS_METRIC,
+            "Unix time of the last successful sync run.",
+            timestamp,
+        );
+    }
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    std::fs::write(&temp, text).with_context(|| {
+        format!(
+            "Failed to write metrics file: {}",
+            Path::new(&temp).display()
+        )
+    })?;
+    std::fs::rename(&temp, path)
+        .with_context(|| format!("Failed to write metrics file: {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Last success timestamp recorded in an existing metrics file, if any
+fn previous_last_success(path: &Path, labels: &str) -> Option<i64> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let prefix = format!("{}{} ", LAST_SUCCESS_METRIC, labels);
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix(&prefix))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Escape a Prometheus label value
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
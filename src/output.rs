@@ -0,0 +1,50 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether informational output is suppressed (`--quiet`)
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable quiet mode
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Check whether quiet mode is enabled
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Print a document as pretty JSON, or as YAML when `format` is "yaml".
+///
+/// serde_yaml quotes strings that would otherwise read back as another type
+/// (e.g. `true`, `0123`), so string values round-trip unchanged.
+pub fn print_document<T: serde::Serialize>(document: &T, format: &str) -> anyhow::Result<()> {
+    if format == "yaml" {
+        print!("{}", serde_yaml::to_string(document)?);
+    } else {
+        println!("{}", serde_json::to_string_pretty(document)?);
+    }
+    Ok(())
+}
+
+/// Print an informational line to stdout unless `--quiet` is set.
+///
+/// Use plain `println!` for the data a command was asked to produce.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !$crate::output::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Like [`status!`] but without a trailing newline
+macro_rules! status_inline {
+    ($($arg:tt)*) => {
+        if !$crate::output::is_quiet() {
+            print!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use status;
+pub(crate) use status_inline;
@@ -0,0 +1,38 @@
+use anyhow::{Context, Result};
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::{SdkTracerProvider, Tracer};
+use opentelemetry_sdk::Resource;
+
+/// Path the OTLP/HTTP receiver accepts traces on
+const TRACES_PATH: &str = "/v1/traces";
+
+/// Tracer provider exporting spans over OTLP/HTTP to `endpoint`, either a
+/// collector's base URL (`http://localhost:4318`) or its full traces URL.
+///
+/// Spans are exported in batches in the background; call
+/// [`SdkTracerProvider::shutdown`] before exiting to flush them.
+pub fn otlp_tracer_provider(endpoint: &str) -> Result<SdkTracerProvider> {
+    let endpoint = endpoint.trim_end_matches('/');
+    let endpoint = if endpoint.ends_with(TRACES_PATH) {
+        endpoint.to_string()
+    } else {
+        format!("{}{}", endpoint, TRACES_PATH)
+    };
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(&endpoint)
+        .build()
+        .with_context(|| format!("Failed to create OTLP exporter for {}", endpoint))?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name("ksecret").build())
+        .build())
+}
+
+/// Tracer used by the `tracing` OpenTelemetry layer
+pub fn tracer(provider: &SdkTracerProvider) -> Tracer {
+    provider.tracer(env!("CARGO_PKG_NAME"))
+}
@@ -0,0 +1,34 @@
+use std::future::Future;
+use std::time::Duration;
+
+/// Error returned when a single GCP or Kubernetes operation exceeds `--timeout`
+#[derive(Debug, thiserror::Error)]
+#[error("Operation timed out after {}s", .0.as_secs())]
+pub struct TimeoutError(pub Duration);
+
+/// Adds an optional per-operation timeout to fallible futures
+pub trait TimeoutExt<T, E>: Future<Output = Result<T, E>> + Sized
+where
+    E: Into<anyhow::Error>,
+{
+    /// Await the future, failing with [`TimeoutError`] if `limit` elapses first.
+    /// `None` waits indefinitely.
+    fn with_timeout(self, limit: Option<Duration>) -> impl Future<Output = anyhow::Result<T>> {
+        async move {
+            let result = match limit {
+                Some(limit) => tokio::time::timeout(limit, self)
+                    .await
+                    .map_err(|_| TimeoutError(limit))?,
+                None => self.await,
+            };
+            result.map_err(Into::into)
+        }
+    }
+}
+
+impl<F, T, E> TimeoutExt<T, E> for F
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<anyhow::Error>,
+{
+}